@@ -0,0 +1,439 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::export::preference_rank;
+use super::model::{Id, Student};
+use super::project_file;
+use super::projects::{self, ProjectInfo};
+use super::State;
+use std::cell::RefCell;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
+use yew::services::storage::{Area, StorageService};
+
+pub struct CarryOver {
+    pub student_id: Id,
+    pub placed: bool,
+    // The best preference the student got last time, none when they were not
+    // placed in any of their preferences.
+    pub rank: Option<usize>,
+    // The student gave up their seat themselves.
+    pub released: bool,
+    pub previous_priority: u32,
+    pub priority: u32,
+}
+
+// The students that were found in the previous project, and the ids of the
+// students that were not found or could be more than one student there.
+#[derive(Default)]
+pub struct CarryOverResult {
+    pub carry_over: Vec<CarryOver>,
+    pub ambiguous: Vec<Id>,
+    pub unmatched: Vec<Id>,
+}
+
+// Students are matched by name and class, as every project has its own ids.
+// When that still fits more than one student, nothing is carried over for
+// them rather than guessing. A student that got their first or second choice
+// starts over without priority, the others get extra priority on top of what
+// they had, so a student that keeps losing the lottery moves up every time.
+pub fn carry_over(previous: &State, students: &[Student]) -> Result<CarryOverResult, String> {
+    let match_result = match &previous.match_result {
+        Some(match_result) => match_result.borrow(),
+        None => return Err("Dit project heeft nog geen indeling.".to_string()),
+    };
+    let previous_students = previous.students.borrow();
+
+    let mut result = CarryOverResult::default();
+    for student in students {
+        let found: Vec<&Student> = previous_students
+            .iter()
+            .filter(|s| s.name == student.name && s.class == student.class)
+            .collect();
+        let previous_student = match found.as_slice() {
+            [previous_student] => previous_student,
+            [] => {
+                result.unmatched.push(student.id);
+                continue;
+            }
+            _ => {
+                result.ambiguous.push(student.id);
+                continue;
+            }
+        };
+        let placements: Vec<Id> = match_result
+            .placed
+            .iter()
+            .filter(|(_, s)| s.contains(&previous_student.id))
+            .map(|(category_id, _)| *category_id)
+            .collect();
+        let rank = placements
+            .iter()
+            .filter_map(|category_id| preference_rank(previous_student, *category_id))
+            .min();
+        // A student that gave up their seat was not unlucky in the lottery,
+        // so they keep the priority they had.
+        let released = match_result.released.contains(&previous_student.id);
+        let priority = match rank {
+            _ if released => previous_student.priority,
+            Some(1) | Some(2) => 0,
+            Some(3) => previous_student.priority + 1,
+            _ => previous_student.priority + 2,
+        };
+        result.carry_over.push(CarryOver {
+            student_id: student.id,
+            placed: !placements.is_empty(),
+            rank,
+            released,
+            previous_priority: previous_student.priority,
+            priority,
+        });
+    }
+    Ok(result)
+}
+
+pub struct Preview {
+    source: String,
+    result: CarryOverResult,
+}
+
+pub struct CarryOverBlock {
+    props: Props,
+    link: ComponentLink<Self>,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+    file_input_ref: NodeRef,
+    preview: Option<Preview>,
+    error: Option<String>,
+}
+
+pub enum Msg {
+    SelectProject(ChangeData),
+    SelectFile(ChangeData),
+    FileLoaded(FileData),
+    Apply,
+    Cancel,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub projects: Vec<ProjectInfo>,
+    pub current: u32,
+    pub students: RefCell<Vec<Student>>,
+    pub on_set_priorities: Callback<Vec<(Id, u32)>>,
+    pub editing: bool,
+}
+
+impl Component for CarryOverBlock {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            reader: ReaderService::new(),
+            reader_task: None,
+            file_input_ref: NodeRef::default(),
+            preview: None,
+            error: None,
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::SelectProject(ChangeData::Select(select)) => {
+                let project = select
+                    .value()
+                    .parse::<u32>()
+                    .ok()
+                    .and_then(|id| self.props.projects.iter().find(|p| p.id == id));
+                if let Some(project) = project {
+                    match StorageService::new(Area::Local) {
+                        Ok(storage) => {
+                            let previous = projects::restore_state(&storage, project.id);
+                            self.show_preview(&project.name.clone(), &previous);
+                        }
+                        Err(e) => self.error = Some(e.to_string()),
+                    }
+                }
+                select.set_value("");
+                true
+            }
+            Msg::SelectProject(_) => false,
+            Msg::SelectFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::FileLoaded);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.reader_task = Some(task),
+                        Err(e) => {
+                            log::error!("Could not read file: {:?}", e);
+                            self.error = Some("Het bestand kon niet worden gelezen.".into());
+                        }
+                    }
+                }
+                true
+            }
+            Msg::SelectFile(_) => false,
+            Msg::FileLoaded(file_data) => {
+                log::info!("Carrying over priorities from {:?}", &file_data.name);
+                self.reader_task = None;
+                self.clear_file_input();
+                match project_file::from_json(&file_data.content) {
+                    Ok(previous) => self.show_preview(&file_data.name, &previous),
+                    Err(error) => {
+                        self.preview = None;
+                        self.error = Some(error);
+                    }
+                }
+                true
+            }
+            Msg::Apply => {
+                if let Some(preview) = self.preview.take() {
+                    self.props.on_set_priorities.emit(
+                        preview
+                            .result
+                            .carry_over
+                            .iter()
+                            .map(|c| (c.student_id, c.priority))
+                            .collect(),
+                    );
+                }
+                true
+            }
+            Msg::Cancel => {
+                self.preview = None;
+                self.error = None;
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let handle_select_project = self.link.callback(Msg::SelectProject);
+        let handle_select_file = self.link.callback(Msg::SelectFile);
+
+        html! {
+            <div class="row d-print-none">
+                <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <h2 class="mb-3">{ "Voorrang uit een vorige indeling" }</h2>
+                    <p>{ "Deelt dezelfde groep leerlingen vaker activiteiten? Kies dan de vorige indeling. Leerlingen die toen hun eerste of tweede voorkeur kregen, hebben daarna geen voorrang meer. Wie de derde voorkeur kreeg, krijgt er 1 voorrang bij en wie een lagere of geen van de voorkeuren kreeg 2. Wie zelf een plek heeft vrijgegeven, houdt de voorrang van de vorige keer. Zo blijft niemand het hele jaar achteraan in de loting staan. De leerlingen worden op naam en klas gevonden." }</p>
+                    <form class="form-inline">
+                        <select class="form-control mr-3 mb-2" disabled=self.props.editing onchange=handle_select_project>
+                            <option value="" selected=true>{ "Kies een project" }</option>
+                            {
+                                self.props.projects.iter().filter(|p| p.id != self.props.current).map(|project| {
+                                    html! { <option value=project.id>{ &project.name }</option> }
+                                }).collect::<Html>()
+                            }
+                        </select>
+                        <label class="mr-2 mb-2">{ "of een opgeslagen projectbestand" }</label>
+                        <input type="file" class="form-control-file mb-2" style="width: auto;" accept=".json,application/json" disabled=self.props.editing onchange=handle_select_file ref=self.file_input_ref.clone() />
+                    </form>
+                    {
+                        if let Some(error) = &self.error {
+                            html! { <div class="invalid-feedback d-block">{ error }</div> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    {
+                        if let Some(preview) = &self.preview {
+                            self.view_preview(preview)
+                        } else {
+                            html! {}
+                        }
+                    }
+                </div>
+            </div>
+        }
+    }
+}
+
+impl CarryOverBlock {
+    fn show_preview(&mut self, source: &str, previous: &State) {
+        match carry_over(previous, &self.props.students.borrow()) {
+            Ok(result) => {
+                self.preview = Some(Preview {
+                    source: source.to_string(),
+                    result,
+                });
+                self.error = None;
+            }
+            Err(error) => {
+                self.preview = None;
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn view_preview(&self, preview: &Preview) -> Html {
+        let handle_apply = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Apply
+        });
+        let handle_cancel = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Cancel
+        });
+
+        let students = self.props.students.borrow();
+        let outcome = |carry_over: &CarryOver| match (carry_over.placed, carry_over.rank) {
+            _ if carry_over.released => "Plek vrijgegeven".to_string(),
+            (_, Some(rank)) => format!("{}e voorkeur", rank),
+            (true, None) => "Geen van de voorkeuren".to_string(),
+            (false, None) => "Niet ingedeeld".to_string(),
+        };
+
+        html! {
+            <div class="border rounded p-3 mt-3">
+                <h5>{ format!("Voorrang op basis van {}", preview.source) }</h5>
+                <p>{ format!("{} van de {} leerlingen zijn in de vorige indeling gevonden.", preview.result.carry_over.len(), students.len()) }</p>
+                <table class="table table-sm table-responsive-sm">
+                    <tr>
+                        <th>{ "Naam leerling" }</th>
+                        <th>{ "Vorige keer" }</th>
+                        <th class="text-right">{ "Voorrang vorige keer" }</th>
+                        <th class="text-right">{ "Nieuwe voorrang" }</th>
+                    </tr>
+                    <tbody>
+                    {
+                        preview.result.carry_over.iter().filter_map(|c| Some((c, students.iter().find(|s| s.id == c.student_id)?))).map(|(carry_over, student)| {
+                            html! {
+                                <tr>
+                                    <td>{ &student.name }</td>
+                                    <td>{ outcome(carry_over) }</td>
+                                    <td class="text-right">{ carry_over.previous_priority }</td>
+                                    <td class="text-right">{ carry_over.priority }</td>
+                                </tr>
+                            }
+                        }).collect::<Html>()
+                    }
+                    </tbody>
+                </table>
+                { self.view_not_found("Meer dan één leerling met deze naam en klas gevonden, hun voorrang blijft ongewijzigd:", &preview.result.ambiguous) }
+                { self.view_not_found("Niet gevonden in de vorige indeling, hun voorrang blijft ongewijzigd:", &preview.result.unmatched) }
+                <button class="btn btn-primary mr-2" onclick=handle_apply disabled=preview.result.carry_over.is_empty()>{ "Voorrang overnemen" }</button>
+                <button class="btn btn-secondary" onclick=handle_cancel>{ "Annuleren" }</button>
+            </div>
+        }
+    }
+
+    fn view_not_found(&self, message: &str, student_ids: &[Id]) -> Html {
+        if student_ids.is_empty() {
+            return html! {};
+        }
+        let students = self.props.students.borrow();
+        let names: Vec<String> = student_ids
+            .iter()
+            .filter_map(|id| students.iter().find(|s| s.id == *id))
+            .map(|s| {
+                if s.class.is_empty() {
+                    s.name.clone()
+                } else {
+                    format!("{} ({})", s.name, s.class)
+                }
+            })
+            .collect();
+        html! {
+            <div class="alert alert-warning">
+                <p class="mb-1">{ message }</p>
+                <p class="mb-0">{ names.join(", ") }</p>
+            </div>
+        }
+    }
+
+    fn clear_file_input(&self) {
+        if let Some(input) = self.file_input_ref.cast::<HtmlInputElement>() {
+            input.set_value("");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::model::MatchResult;
+    use std::collections::BTreeMap;
+
+    fn student(id: Id, name: &str, class: &str, preferences: &[Id]) -> Student {
+        let mut student = Student::new(name, preferences.to_vec(), vec![]);
+        student.id = id;
+        student.class = class.to_string();
+        student
+    }
+
+    #[test]
+    fn students_are_found_by_name_and_class() {
+        let mut placed = BTreeMap::new();
+        placed.insert(1, vec![1, 2, 3]);
+        placed.insert(2, vec![4]);
+        placed.insert(3, vec![6]);
+        let previous = State {
+            students: RefCell::new(vec![
+                student(1, "Sam", "1A", &[2, 1]),
+                student(2, "Sam", "1B", &[1]),
+                student(3, "Noor", "1A", &[1]),
+                student(4, "Noor", "1A", &[1]),
+                student(5, "Lies", "1A", &[]),
+                student(6, "Jip", "1A", &[1, 2, 3]),
+                student(7, "Bo", "1B", &[1]),
+            ]),
+            match_result: Some(RefCell::new(MatchResult {
+                placed,
+                not_placable: vec![5],
+                released: vec![7],
+                ..MatchResult::default()
+            })),
+            ..State::default()
+        };
+        // Priorities are added to what the students already had, except for
+        // a student that released their seat.
+        for (index, priority) in [(4, 3), (5, 1), (6, 1)].iter() {
+            previous.students.borrow_mut()[*index].priority = *priority;
+        }
+        let students = vec![
+            student(10, "Sam", "1A", &[]),
+            student(11, "Sam", "1B", &[]),
+            student(12, "Noor", "1A", &[]),
+            student(13, "Lies", "1A", &[]),
+            student(14, "Sam", "1C", &[]),
+            student(15, "Jip", "1A", &[]),
+            student(16, "Bo", "1B", &[]),
+        ];
+
+        let result = carry_over(&previous, &students).unwrap();
+        let priorities: Vec<(Id, u32)> = result
+            .carry_over
+            .iter()
+            .map(|c| (c.student_id, c.priority))
+            .collect();
+        assert_eq!(
+            priorities,
+            vec![(10, 0), (11, 0), (13, 5), (15, 2), (16, 1)]
+        );
+        assert!(result.carry_over[4].released);
+        assert_eq!(result.ambiguous, vec![12]);
+        assert_eq!(result.unmatched, vec![14]);
+    }
+}
@@ -15,8 +15,9 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::category_import::CategoryImport;
 use super::category_row::CategoryRow;
-use matchmaker::Category;
+use super::model::{Category, Id, Quota};
 use std::cell::RefCell;
 use wasm_bindgen::JsCast;
 use web_sys::{FormData, HtmlFormElement, HtmlInputElement};
@@ -25,18 +26,33 @@ use yew::prelude::*;
 pub enum FieldError {
     Duplicate,
     Empty,
+    MinimumTooHigh,
+}
+
+impl FieldError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            FieldError::Duplicate => "Er bestaat al een activiteit met deze naam!",
+            FieldError::Empty => "Een naam en aantal beschikbare plekken is noodzakelijk!",
+            FieldError::MinimumTooHigh => {
+                "Het minimum aantal deelnemers kan niet groter zijn dan het aantal plekken!"
+            }
+        }
+    }
 }
 
 pub struct State {
     error: Option<FieldError>,
     name: String,
     max_placements: String,
+    min_placements: String,
 }
 
 pub struct CategoryBlock {
     props: Props,
     link: ComponentLink<Self>,
     name_input_ref: NodeRef,
+    rounds_input_ref: NodeRef,
     state: State,
 }
 
@@ -48,10 +64,17 @@ pub enum Msg {
 pub struct Props {
     pub categories: RefCell<Vec<Category>>,
     pub on_add_category: Callback<Category>,
-    pub on_remove_category: Callback<Category>,
+    pub on_add_categories: Callback<Vec<Category>>,
+    pub on_remove_category: Callback<Id>,
     pub editing: bool,
-    pub on_edit_category: Callback<(String, String, Option<usize>)>,
+    pub on_edit_category: Callback<(Id, String, Option<usize>, usize)>,
     pub on_editing: Callback<()>,
+    pub rounds: usize,
+    pub rounds_error: Option<String>,
+    pub on_set_rounds: Callback<usize>,
+    pub on_toggle_category_slot: Callback<(Id, usize)>,
+    pub on_set_quota: Callback<(Id, Option<Quota>)>,
+    pub on_set_category_eligibility: Callback<(Id, Vec<String>, Vec<String>)>,
 }
 
 impl Component for CategoryBlock {
@@ -63,10 +86,12 @@ impl Component for CategoryBlock {
             props,
             link,
             name_input_ref: NodeRef::default(),
+            rounds_input_ref: NodeRef::default(),
             state: State {
                 error: None,
                 name: "".into(),
                 max_placements: "".into(),
+                min_placements: "".into(),
             },
         }
     }
@@ -74,7 +99,7 @@ impl Component for CategoryBlock {
     fn update(&mut self, message: Self::Message) -> ShouldRender {
         match message {
             Msg::AddCategory(form_data) => {
-                let name = form_data.get("category_name").as_string().map(|n| n);
+                let name = form_data.get("category_name").as_string();
                 let max_placements_string = form_data.get("category_max_placements").as_string();
                 let max_placements = max_placements_string
                     .clone()
@@ -82,26 +107,29 @@ impl Component for CategoryBlock {
 
                 self.state.name = name.clone().unwrap_or("".to_string());
                 self.state.max_placements = max_placements_string.unwrap_or("".to_string());
+                self.state.min_placements = form_data
+                    .get("category_min_placements")
+                    .as_string()
+                    .unwrap_or("".to_string());
+                let min_placements = self.state.min_placements.parse::<usize>().unwrap_or(0);
 
                 if let Some(name) = name {
-                    if let Some(max_placements) = max_placements {
-                        if name != "" && max_placements != 0 {
-                            let category = Category::new(name.trim(), max_placements);
-                            if self.props.categories.borrow().contains(&category) {
-                                self.state.error = Some(FieldError::Duplicate);
-                            } else {
-                                self.props.on_add_category.emit(category);
-                                self.focus_on_input();
-                                self.state.error = None;
-                                self.state.name = "".into();
-                                self.state.max_placements = "".into();
-                                return false;
-                            }
-                        } else {
-                            self.state.error = Some(FieldError::Empty);
+                    match validate_category(&name, max_placements, &self.props.categories.borrow())
+                    {
+                        Ok(category) if min_placements > category.max_placements => {
+                            self.state.error = Some(FieldError::MinimumTooHigh)
                         }
-                    } else {
-                        self.state.error = Some(FieldError::Empty);
+                        Ok(mut category) => {
+                            category.min_placements = min_placements;
+                            self.props.on_add_category.emit(category);
+                            self.focus_on_input();
+                            self.state.error = None;
+                            self.state.name = "".into();
+                            self.state.max_placements = "".into();
+                            self.state.min_placements = "".into();
+                            return false;
+                        }
+                        Err(error) => self.state.error = Some(error),
                     }
                 }
                 true
@@ -115,6 +143,15 @@ impl Component for CategoryBlock {
     }
 
     fn view(&self) -> Html {
+        let on_set_rounds = self.props.on_set_rounds.clone();
+        let handle_set_rounds = Callback::from(move |change_data: ChangeData| {
+            if let ChangeData::Value(value) = change_data {
+                if let Ok(rounds) = value.parse::<usize>() {
+                    on_set_rounds.emit(rounds.max(1));
+                }
+            }
+        });
+
         let categories: Vec<Html> = self
             .props
             .categories
@@ -122,7 +159,7 @@ impl Component for CategoryBlock {
             .iter()
             .map(|category: &Category| {
                 html! {
-                    <CategoryRow category=category.clone() editing=self.props.editing.clone() on_edit_category=self.props.on_edit_category.clone() on_remove_category=self.props.on_remove_category.clone() on_editing=self.props.on_editing.clone() />
+                    <CategoryRow category=category.clone() editing=self.props.editing.clone() on_edit_category=self.props.on_edit_category.clone() on_remove_category=self.props.on_remove_category.clone() on_editing=self.props.on_editing.clone() rounds=self.props.rounds on_toggle_slot=self.props.on_toggle_category_slot.clone() on_set_quota=self.props.on_set_quota.clone() on_set_eligibility=self.props.on_set_category_eligibility.clone() />
                 }
             })
             .collect();
@@ -145,9 +182,9 @@ impl Component for CategoryBlock {
                         .unwrap()
                         .clone(),
                 )
-                .or_else(|e| {
+                .map_err(|e| {
                     log::info!("FormData can not be created: {:?}", e);
-                    Err(e)
+                    e
                 })
                 .unwrap(),
             )
@@ -156,11 +193,35 @@ impl Component for CategoryBlock {
         html! {
             <div class="col shadow p-3 mb-5 bg-white rounded">
                 <h2>{"Stap 1: Activiteiten toevoegen"}</h2>
-                <p>{ "Voeg bij 'Naam activiteit' de verschillende activiteiten toe waar de leerlingen aan kunnen deelnemen. Vul per activiteit het maximum aantal leerlingen in." }</p>
+                <p>{ "Voeg bij 'Naam activiteit' de verschillende activiteiten toe waar de leerlingen aan kunnen deelnemen. Vul per activiteit het maximum aantal leerlingen in. Vul eventueel ook een minimum in: een activiteit met te weinig deelnemers gaat dan niet door en de leerlingen worden opnieuw ingedeeld bij hun andere voorkeuren." }</p>
+                <p>{ "Vinden de activiteiten plaats in meerdere rondes, zoals op een sportdag? Vul dan het aantal rondes in en geef per activiteit aan in welke rondes deze plaatsvindt. Iedere leerling krijgt dan per ronde één activiteit en doet geen activiteit twee keer." }</p>
+                <p>{ "Wil je dat een activiteit gemengd is? Vul dan bij 'Max. aandeel' in welk percentage van de plekken hoogstens naar leerlingen uit één klas mag gaan. Vul je daaronder een kenmerk in, dan geldt het percentage voor de leerlingen met dat kenmerk." }</p>
+                <p>{ "Is een activiteit niet voor iedereen? Vul dan bij 'Alleen voor' de klassen in die mogen meedoen, of de kenmerken die leerlingen moeten hebben, zoals 'zwemdiploma'. De kenmerken van een leerling vul je in bij stap 2." }</p>
+                <form class="form-inline mb-3">
+                    <label class="mr-2" for="rounds">{ "Aantal rondes" }</label>
+                    <input type="number" class="form-control form-control-sm" id="rounds" min="1" value=self.props.rounds disabled=self.props.editing onchange=handle_set_rounds ref=self.rounds_input_ref.clone() />
+                    {
+                        if let Some(error) = &self.props.rounds_error {
+                            html! { <div class="invalid-feedback d-block">{ error }</div> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </form>
                 <table class="table table-responsive-sm">
                     <tr>
                         <th>{ "Naam activiteit" }</th>
                         <th>{ "Aantal plekken" }</th>
+                        <th title="De activiteit gaat niet door als er minder leerlingen worden ingedeeld.">{ "Minimum" }</th>
+                        <th title="Het deel van de plekken dat naar leerlingen uit één klas, of naar leerlingen met een kenmerk, mag gaan. Een harde grens wordt nooit overschreden, een zachte alleen als er anders plekken leeg blijven.">{ "Max. aandeel" }</th>
+                        <th title="Leerlingen die niet aan de voorwaarden voldoen, worden niet bij de activiteit ingedeeld.">{ "Alleen voor" }</th>
+                        {
+                            if self.props.rounds > 1 {
+                                html! { <th title="De rondes waarin de activiteit plaatsvindt. Het aantal plekken geldt per ronde.">{ "Rondes" }</th> }
+                            } else {
+                                html! {}
+                            }
+                        }
                         <th class="control"></th>
                     </tr>
                     <tbody>
@@ -176,23 +237,21 @@ impl Component for CategoryBlock {
                     <div class="form-group mb-2 mx-sm-3">
                         <input type="number" class="form-control" name="category_max_placements" value=&self.state.max_placements disabled=self.props.editing id="category_max_placements" placeholder="Aantal plekken" />
                     </div>
+                    <div class="form-group mb-2 mr-sm-3">
+                        <input type="number" class="form-control" name="category_min_placements" min="0" value=&self.state.min_placements disabled=self.props.editing id="category_min_placements" placeholder="Minimum (optioneel)" />
+                    </div>
                     <button type="submit" class="btn btn-primary mb-2" disabled=self.props.editing>{ "Toevoegen" }</button>
-                    { if self.state.error.is_some() {
+                    { if let Some(error) = &self.state.error {
                         html! {
                             <div class="invalid-feedback d-block">
-                                {
-                                    match self.state.error {
-                                        Some(FieldError::Duplicate) => "Er bestaat al een activiteit met deze naam!",
-                                        Some(FieldError::Empty) => "Een naam en aantal beschikbare plekken is noodzakelijk!",
-                                        _ => "Onbekende fout",
-                                    }
-                                }
+                                { error.message() }
                             </div>
                         }
                     } else {
                         html! {}
                     } }
                 </form>
+                <CategoryImport categories=self.props.categories.clone() on_add_categories=self.props.on_add_categories.clone() editing=self.props.editing />
             </div>
         }
     }
@@ -201,6 +260,10 @@ impl Component for CategoryBlock {
         if first_render {
             self.focus_on_input();
         }
+        // A rejected number of rounds is not kept in the input.
+        if let Some(input) = self.rounds_input_ref.cast::<HtmlInputElement>() {
+            input.set_value(&self.props.rounds.to_string());
+        }
     }
 }
 
@@ -211,3 +274,21 @@ impl CategoryBlock {
         }
     }
 }
+
+pub fn validate_category(
+    name: &str,
+    max_placements: Option<usize>,
+    categories: &[Category],
+) -> Result<Category, FieldError> {
+    let name = name.trim();
+    match max_placements {
+        Some(max_placements) if !name.is_empty() && max_placements != 0 => {
+            if categories.iter().any(|c| c.name == name) {
+                Err(FieldError::Duplicate)
+            } else {
+                Ok(Category::new(name, max_placements))
+            }
+        }
+        _ => Err(FieldError::Empty),
+    }
+}
@@ -0,0 +1,287 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::category_block::{validate_category, FieldError};
+use super::import::{read_rows, Row};
+use super::model::Category;
+use std::cell::RefCell;
+use wasm_bindgen::JsCast;
+use web_sys::{FormData, HtmlFormElement, HtmlInputElement};
+use yew::prelude::*;
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
+
+pub struct Report {
+    added: usize,
+    errors: Vec<(u64, String, FieldError)>,
+}
+
+pub struct State {
+    rows: String,
+    report: Option<Report>,
+    error: Option<String>,
+}
+
+pub struct CategoryImport {
+    props: Props,
+    link: ComponentLink<Self>,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+    file_input_ref: NodeRef,
+    state: State,
+}
+
+pub enum Msg {
+    ImportRows(FormData),
+    SelectFile(ChangeData),
+    FileLoaded(FileData),
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub categories: RefCell<Vec<Category>>,
+    pub on_add_categories: Callback<Vec<Category>>,
+    pub editing: bool,
+}
+
+impl Component for CategoryImport {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            reader: ReaderService::new(),
+            reader_task: None,
+            file_input_ref: NodeRef::default(),
+            state: State {
+                rows: "".into(),
+                report: None,
+                error: None,
+            },
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::ImportRows(form_data) => {
+                let rows = form_data
+                    .get("category_rows")
+                    .as_string()
+                    .unwrap_or_default();
+                self.import(&rows);
+                true
+            }
+            Msg::SelectFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::FileLoaded);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.reader_task = Some(task),
+                        Err(e) => {
+                            log::error!("Could not read file: {:?}", e);
+                            self.state.error = Some("Het bestand kon niet worden gelezen.".into());
+                        }
+                    }
+                }
+                true
+            }
+            Msg::SelectFile(_) => false,
+            Msg::FileLoaded(file_data) => {
+                log::info!("Importing categories from {:?}", &file_data.name);
+                self.reader_task = None;
+                self.import(&String::from_utf8_lossy(&file_data.content));
+                if let Some(input) = self.file_input_ref.cast::<HtmlInputElement>() {
+                    input.set_value("");
+                }
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let submit_form = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::ImportRows(
+                FormData::new_with_form(
+                    &e.target()
+                        .and_then(|t| t.dyn_into::<HtmlFormElement>().ok())
+                        .expect("import form"),
+                )
+                .expect("FormData can not be created"),
+            )
+        });
+        let handle_select_file = self.link.callback(Msg::SelectFile);
+
+        html! {
+            <>
+                <h5 class="mt-4">{ "Meerdere activiteiten tegelijk toevoegen" }</h5>
+                <p>{ "Plak hieronder de activiteiten vanuit een spreadsheet, met de naam van de activiteit in de eerste kolom en het aantal plekken in de tweede kolom. Je kunt ook een CSV-bestand met dezelfde kolommen kiezen." }</p>
+                <form novalidate=true onsubmit=submit_form>
+                    <div class="form-group mb-2">
+                        <textarea class="form-control" name="category_rows" rows="4" disabled=self.props.editing placeholder="Naam activiteit, aantal plekken" value=&self.state.rows></textarea>
+                    </div>
+                    <div class="form-inline">
+                        <button type="submit" class="btn btn-primary mb-2 mr-sm-3" disabled=self.props.editing>{ "Importeren" }</button>
+                        <input type="file" class="form-control-file mb-2 w-auto" accept=".csv,.txt,text/csv" disabled=self.props.editing onchange=handle_select_file ref=self.file_input_ref.clone() />
+                    </div>
+                </form>
+                {
+                    if let Some(error) = &self.state.error {
+                        html! { <div class="invalid-feedback d-block">{ error }</div> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if let Some(report) = &self.state.report {
+                        html! {
+                            <>
+                                <div class="valid-feedback d-block">{ format!("{} activiteiten toegevoegd.", report.added) }</div>
+                                {
+                                    report.errors.iter().map(|(line, name, error)| {
+                                        html! {
+                                            <div class="invalid-feedback d-block">{ format!("Regel {} ({}): {}", line, name, error.message()) }</div>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                            </>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
+}
+
+impl CategoryImport {
+    fn import(&mut self, content: &str) {
+        let rows = match read_rows(content) {
+            Ok(rows) => rows,
+            Err(error) => {
+                self.state.report = None;
+                self.state.error = Some(error);
+                return;
+            }
+        };
+
+        let (added, report, failed_rows) = import_rows(&rows, &self.props.categories.borrow());
+
+        // All rows are added at once, so the import is undone in one step.
+        if !added.is_empty() {
+            self.props.on_add_categories.emit(added);
+        }
+
+        // Keep the failing lines so they can be corrected and imported again.
+        self.state.rows = failed_rows.join("\n");
+        self.state.report = Some(report);
+        self.state.error = None;
+    }
+}
+
+// The categories that can be added, the report and the failing lines.
+fn import_rows(rows: &[Row], existing: &[Category]) -> (Vec<Category>, Report, Vec<String>) {
+    let mut categories = existing.to_vec();
+    let mut added = vec![];
+    let mut report = Report {
+        added: 0,
+        errors: vec![],
+    };
+    let mut failed_rows = vec![];
+
+    for (i, row) in rows.iter().enumerate() {
+        if row.is_empty() || (i == 0 && is_header(row)) {
+            continue;
+        }
+        let max_placements = row.field(1).parse::<usize>().ok();
+        match validate_category(row.field(0), max_placements, &categories) {
+            Ok(category) => {
+                categories.push(category.clone());
+                added.push(category);
+                report.added += 1;
+            }
+            Err(error) => {
+                report
+                    .errors
+                    .push((row.line, row.field(0).to_string(), error));
+                failed_rows.push(row.fields.join("\t"));
+            }
+        }
+    }
+
+    (added, report, failed_rows)
+}
+
+// Only a first cell that is exactly a column name counts, so an activity
+// such as "Activiteit koken" with a typo in its places is reported instead
+// of skipped.
+fn is_header(row: &Row) -> bool {
+    row.field(1).parse::<usize>().is_err()
+        && matches!(
+            row.field(0).to_lowercase().as_str(),
+            "naam" | "naam activiteit" | "activiteit" | "activiteiten" | "name" | "activity"
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(content: &str) -> (Vec<Category>, Report, Vec<String>) {
+        import_rows(&read_rows(content).unwrap(), &[Category::new("Dans", 5)])
+    }
+
+    #[test]
+    fn the_header_is_skipped() {
+        let (added, report, failed_rows) = import("Naam activiteit;Plekken\nKoken;12\n");
+        let names: Vec<&str> = added.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, vec!["Koken"]);
+        assert_eq!(added[0].max_placements, 12);
+        assert!(report.errors.is_empty());
+        assert!(failed_rows.is_empty());
+    }
+
+    #[test]
+    fn a_first_row_with_bad_places_is_reported() {
+        let (added, report, failed_rows) = import("Activiteit koken;twaalf\nToneel;8\n");
+        assert_eq!(added.len(), 1);
+        assert_eq!(report.added, 1);
+        assert_eq!(report.errors.len(), 1);
+        let (line, name, error) = &report.errors[0];
+        assert_eq!((*line, name.as_str()), (1, "Activiteit koken"));
+        assert_eq!(error.message(), FieldError::Empty.message());
+        assert_eq!(failed_rows, vec!["Activiteit koken\ttwaalf"]);
+    }
+
+    #[test]
+    fn empty_rows_are_skipped_and_duplicates_reported() {
+        let (added, report, _) = import("Koken;12\n;\nDans;4\nKoken;3\n");
+        assert_eq!(added.len(), 1);
+        let lines: Vec<u64> = report.errors.iter().map(|(line, _, _)| *line).collect();
+        assert_eq!(lines, vec![3, 4]);
+        assert!(report
+            .errors
+            .iter()
+            .all(|(_, _, error)| error.message() == FieldError::Duplicate.message()));
+    }
+}
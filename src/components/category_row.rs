@@ -15,13 +15,14 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
-use matchmaker::Category;
+use super::category_block::FieldError;
+use super::model::{parse_list, Category, Id, Quota, QuotaAttribute};
 use web_sys::HtmlInputElement;
 use yew::prelude::*;
 
 pub struct State {
     editing: bool,
-    pre_editing_category_name: String,
+    error: Option<FieldError>,
 }
 
 pub struct CategoryRow {
@@ -30,15 +31,20 @@ pub struct CategoryRow {
     state: State,
     name_input_ref: NodeRef,
     max_placements_input_ref: NodeRef,
+    min_placements_input_ref: NodeRef,
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
-    pub on_edit_category: Callback<(String, String, Option<usize>)>,
+    pub on_edit_category: Callback<(Id, String, Option<usize>, usize)>,
     pub category: Category,
-    pub on_remove_category: Callback<Category>,
+    pub on_remove_category: Callback<Id>,
     pub editing: bool,
     pub on_editing: Callback<()>,
+    pub rounds: usize,
+    pub on_toggle_slot: Callback<(Id, usize)>,
+    pub on_set_quota: Callback<(Id, Option<Quota>)>,
+    pub on_set_eligibility: Callback<(Id, Vec<String>, Vec<String>)>,
 }
 
 #[derive(Debug)]
@@ -46,6 +52,12 @@ pub enum Msg {
     EditCategory,
     EditCategoryEnd,
     RemoveCategory,
+    ToggleSlot(usize),
+    SetQuotaPercentage(ChangeData),
+    ToggleQuotaHard,
+    SetQuotaTag(ChangeData),
+    SetClasses(ChangeData),
+    SetRequiredTags(ChangeData),
 }
 
 impl Component for CategoryRow {
@@ -58,9 +70,10 @@ impl Component for CategoryRow {
             link,
             name_input_ref: NodeRef::default(),
             max_placements_input_ref: NodeRef::default(),
+            min_placements_input_ref: NodeRef::default(),
             state: State {
                 editing: false,
-                pre_editing_category_name: "".to_string(),
+                error: None,
             },
         }
     }
@@ -69,30 +82,120 @@ impl Component for CategoryRow {
         match message {
             Msg::EditCategory => {
                 self.state.editing = true;
-                self.state.pre_editing_category_name = self.props.category.name.clone();
                 self.props.on_editing.emit(());
                 true
             }
             Msg::EditCategoryEnd => {
-                self.state.editing = false;
                 if let Some(name_input) = self.name_input_ref.cast::<HtmlInputElement>() {
-                    if let Some(max_placements_input) =
-                        self.max_placements_input_ref.cast::<HtmlInputElement>()
-                    {
+                    if let (Some(max_placements_input), Some(min_placements_input)) = (
+                        self.max_placements_input_ref.cast::<HtmlInputElement>(),
+                        self.min_placements_input_ref.cast::<HtmlInputElement>(),
+                    ) {
                         let max_placements = max_placements_input.value().parse::<usize>().ok();
+                        let min_placements =
+                            min_placements_input.value().parse::<usize>().unwrap_or(0);
+                        // Also when only the maximum is lowered, the row stays
+                        // in edit mode until the minimum fits.
+                        if min_placements
+                            > max_placements
+                                .filter(|mp| *mp != 0)
+                                .unwrap_or(self.props.category.max_placements)
+                        {
+                            self.state.error = Some(FieldError::MinimumTooHigh);
+                            return true;
+                        }
                         self.props.on_edit_category.emit((
-                            self.state.pre_editing_category_name.clone(),
+                            self.props.category.id,
                             name_input.value(),
                             max_placements,
+                            min_placements,
                         ));
                     }
                 }
+                self.state.editing = false;
+                self.state.error = None;
                 true
             }
             Msg::RemoveCategory => {
+                self.props.on_remove_category.emit(self.props.category.id);
+                false
+            }
+            Msg::ToggleSlot(slot) => {
                 self.props
-                    .on_remove_category
-                    .emit(self.props.category.clone());
+                    .on_toggle_slot
+                    .emit((self.props.category.id, slot));
+                false
+            }
+            Msg::SetQuotaPercentage(ChangeData::Value(value)) => {
+                let (attribute, hard) = self
+                    .props
+                    .category
+                    .quota
+                    .as_ref()
+                    .map(|quota| (quota.attribute.clone(), quota.hard))
+                    .unwrap_or_default();
+                let quota = value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|percentage| *percentage > 0 && *percentage < 100)
+                    .map(|percentage| Quota {
+                        attribute,
+                        percentage,
+                        hard,
+                    });
+                self.props
+                    .on_set_quota
+                    .emit((self.props.category.id, quota));
+                false
+            }
+            Msg::SetQuotaPercentage(_) => false,
+            // Without a tag the quota applies to every class.
+            Msg::SetQuotaTag(ChangeData::Value(value)) => {
+                if let Some(quota) = &self.props.category.quota {
+                    let tag = value.trim();
+                    let attribute = if tag.is_empty() {
+                        QuotaAttribute::Class
+                    } else {
+                        QuotaAttribute::Tag(tag.to_string())
+                    };
+                    self.props.on_set_quota.emit((
+                        self.props.category.id,
+                        Some(Quota {
+                            attribute,
+                            ..quota.clone()
+                        }),
+                    ));
+                }
+                false
+            }
+            Msg::SetQuotaTag(_) => false,
+            Msg::SetClasses(ChangeData::Value(value)) => {
+                self.props.on_set_eligibility.emit((
+                    self.props.category.id,
+                    parse_list(&value),
+                    self.props.category.required_tags.clone(),
+                ));
+                false
+            }
+            Msg::SetRequiredTags(ChangeData::Value(value)) => {
+                self.props.on_set_eligibility.emit((
+                    self.props.category.id,
+                    self.props.category.classes.clone(),
+                    parse_list(&value),
+                ));
+                false
+            }
+            Msg::SetClasses(_) | Msg::SetRequiredTags(_) => false,
+            Msg::ToggleQuotaHard => {
+                if let Some(quota) = &self.props.category.quota {
+                    self.props.on_set_quota.emit((
+                        self.props.category.id,
+                        Some(Quota {
+                            hard: !quota.hard,
+                            ..quota.clone()
+                        }),
+                    ));
+                }
                 false
             }
         }
@@ -124,6 +227,47 @@ impl Component for CategoryRow {
             Msg::RemoveCategory
         });
 
+        let slots: Vec<Html> = (0..self.props.rounds)
+            .map(|slot| {
+                let handle_toggle_slot = self.link.callback(move |_: MouseEvent| Msg::ToggleSlot(slot));
+                // The only round of an activity can not be switched off.
+                let only_round = self.props.category.slots == [slot];
+                html! {
+                    <div class="form-check form-check-inline">
+                        <input type="checkbox" class="form-check-input" id=format!("slot-{}-{}", self.props.category.id, slot) checked=self.props.category.runs_in(slot) disabled=self.props.editing || only_round onclick=handle_toggle_slot />
+                        <label class="form-check-label" for=format!("slot-{}-{}", self.props.category.id, slot)>{ slot + 1 }</label>
+                    </div>
+                }
+            })
+            .collect();
+
+        let handle_set_quota_percentage = self.link.callback(Msg::SetQuotaPercentage);
+        let handle_toggle_quota_hard = self.link.callback(|_: MouseEvent| Msg::ToggleQuotaHard);
+        let quota_percentage = self
+            .props
+            .category
+            .quota
+            .as_ref()
+            .map(|quota| quota.percentage.to_string())
+            .unwrap_or_default();
+        let quota_hard = self
+            .props
+            .category
+            .quota
+            .as_ref()
+            .map(|quota| quota.hard)
+            .unwrap_or(false);
+        let quota_disabled = self.props.editing || self.props.category.quota.is_none();
+        let handle_set_quota_tag = self.link.callback(Msg::SetQuotaTag);
+        let quota_tag = self
+            .props
+            .category
+            .quota_tag()
+            .unwrap_or_default()
+            .to_string();
+        let handle_set_classes = self.link.callback(Msg::SetClasses);
+        let handle_set_required_tags = self.link.callback(Msg::SetRequiredTags);
+
         html! {
             <tr>
                 <td>
@@ -142,7 +286,7 @@ impl Component for CategoryRow {
                     {
                         if self.state.editing {
                             html! {
-                            <form class="inline" onsubmit=handle_on_edit_category_submit>
+                            <form class="inline" onsubmit=handle_on_edit_category_submit.clone()>
                                 <input type="number" name="category_max_placements" value=&self.props.category.max_placements ref=self.max_placements_input_ref.clone() />
                             </form> }
                         } else {
@@ -150,6 +294,53 @@ impl Component for CategoryRow {
                         }
                     }
                 </td>
+                <td>
+                    {
+                        if self.state.editing {
+                            html! {
+                            <form class="inline" onsubmit=handle_on_edit_category_submit>
+                                <input type="number" name="category_min_placements" min="0" value=&self.props.category.min_placements ref=self.min_placements_input_ref.clone() />
+                                {
+                                    if let Some(error) = &self.state.error {
+                                        html! { <div class="invalid-feedback d-block">{ error.message() }</div> }
+                                    } else {
+                                        html! {}
+                                    }
+                                }
+                            </form> }
+                        } else if self.props.category.min_placements > 0 {
+                            html! { &self.props.category.min_placements }
+                        } else {
+                            html! { "-" }
+                        }
+                    }
+                </td>
+                <td>
+                    <form class="form-inline">
+                        <div class="input-group input-group-sm mr-2">
+                            <input type="number" class="form-control" min="1" max="99" size="3" value=quota_percentage disabled=self.props.editing onchange=handle_set_quota_percentage />
+                            <div class="input-group-append">
+                                <span class="input-group-text">{ "%" }</span>
+                            </div>
+                        </div>
+                        <div class="form-check form-check-inline">
+                            <input type="checkbox" class="form-check-input" id=format!("quota-hard-{}", self.props.category.id) checked=quota_hard disabled=quota_disabled onclick=handle_toggle_quota_hard />
+                            <label class="form-check-label" for=format!("quota-hard-{}", self.props.category.id)>{ "hard" }</label>
+                        </div>
+                        <input type="text" class="form-control form-control-sm mt-1" placeholder="Per klas" title="Vul een kenmerk in om alleen het aandeel van leerlingen met dat kenmerk te begrenzen. Laat leeg voor een grens per klas." value=quota_tag disabled=quota_disabled onchange=handle_set_quota_tag />
+                    </form>
+                </td>
+                <td>
+                    <input type="text" class="form-control form-control-sm mb-1" placeholder="Klassen" title="Alleen leerlingen uit deze klassen mogen meedoen, gescheiden door komma's." value=self.props.category.classes.join(", ") disabled=self.props.editing onchange=handle_set_classes />
+                    <input type="text" class="form-control form-control-sm" placeholder="Kenmerken" title="Alleen leerlingen met al deze kenmerken mogen meedoen, gescheiden door komma's." value=self.props.category.required_tags.join(", ") disabled=self.props.editing onchange=handle_set_required_tags />
+                </td>
+                {
+                    if self.props.rounds > 1 {
+                        html! { <td>{ slots }</td> }
+                    } else {
+                        html! {}
+                    }
+                }
                 <td class="control">
                     <form class="inline">
                         {
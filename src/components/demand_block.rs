@@ -0,0 +1,354 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::model::{category_by_id, Category, Id, Student};
+use std::cell::RefCell;
+use yew::prelude::*;
+
+pub struct CategoryDemand {
+    pub category_id: Id,
+    pub first_choice: usize,
+    pub top_choices: usize,
+    pub free_places: usize,
+}
+
+// The places of the activity over all rounds in which it runs, without the
+// places locked students already take.
+fn free_places(category: &Category, students: &[Student], rounds: usize) -> usize {
+    let locked = students
+        .iter()
+        .filter(|s| s.locked == Some(category.id))
+        .count();
+    category.total_places(rounds).saturating_sub(locked)
+}
+
+// Students locked to the activity are left out, their places are already
+// taken off the free places.
+pub fn category_demand(
+    students: &[Student],
+    categories: &[Category],
+    top: usize,
+    rounds: usize,
+) -> Vec<CategoryDemand> {
+    categories
+        .iter()
+        .map(|category| {
+            let ranked_within = |n: usize| {
+                students
+                    .iter()
+                    .filter(|s| s.locked != Some(category.id))
+                    .filter(|s| s.preferences.iter().take(n).any(|c| *c == category.id))
+                    .count()
+            };
+            CategoryDemand {
+                category_id: category.id,
+                first_choice: ranked_within(1),
+                top_choices: ranked_within(top),
+                free_places: free_places(category, students, rounds),
+            }
+        })
+        .collect()
+}
+
+pub fn feasibility_warnings(
+    students: &[Student],
+    categories: &[Category],
+    multi_matches: bool,
+    rounds: usize,
+) -> Vec<String> {
+    let mut warnings = vec![];
+
+    if rounds > 1 {
+        for round in 0..rounds {
+            let capacity: usize = categories
+                .iter()
+                .filter(|c| c.runs_in(round))
+                .map(|c| c.max_placements)
+                .sum();
+            if capacity < students.len() {
+                warnings.push(format!(
+                    "In ronde {} zijn {} plekken voor {} leerlingen. Er kunnen dus minstens {} leerlingen in deze ronde niet worden ingedeeld.",
+                    round + 1,
+                    capacity,
+                    students.len(),
+                    students.len() - capacity
+                ));
+            }
+        }
+        if categories.len() < rounds {
+            warnings.push(format!(
+                "Er zijn {} rondes maar slechts {} activiteiten. Omdat niemand een activiteit twee keer doet, kan niet iedereen in elke ronde worden ingedeeld.",
+                rounds,
+                categories.len()
+            ));
+        }
+    } else {
+        let capacity: usize = categories.iter().map(|c| c.max_placements).sum();
+        if !multi_matches && capacity < students.len() {
+            warnings.push(format!(
+                "Er zijn {} plekken voor {} leerlingen. Er kunnen dus minstens {} leerlingen niet worden ingedeeld.",
+                capacity,
+                students.len(),
+                students.len() - capacity
+            ));
+        }
+    }
+
+    for category in categories {
+        let locked = students
+            .iter()
+            .filter(|s| s.locked == Some(category.id))
+            .count();
+        if locked > category.max_placements {
+            warnings.push(format!(
+                "Bij {} zijn {} leerlingen vastgezet, terwijl er maar {} plekken zijn.",
+                category.name, locked, category.max_placements
+            ));
+        }
+        let interested = students
+            .iter()
+            .filter(|s| s.locked == Some(category.id) || s.preferences.contains(&category.id))
+            .count();
+        if interested < category.min_placements {
+            warnings.push(format!(
+                "{} heeft minimaal {} deelnemers nodig, maar wordt door slechts {} leerlingen gekozen. De activiteit wordt waarschijnlijk geannuleerd.",
+                category.name, category.min_placements, interested
+            ));
+        }
+    }
+
+    for student in students {
+        for other in students
+            .iter()
+            .filter(|o| o.id > student.id && student.separate.contains(&o.id))
+        {
+            let same_lock = student.locked.is_some() && student.locked == other.locked;
+            let same_group = student.group.is_some() && student.group == other.group;
+            if same_lock || same_group {
+                warnings.push(format!(
+                    "{} en {} mogen niet samen worden ingedeeld, maar zijn bij dezelfde activiteit vastgezet of in hetzelfde groepje geplaatst.",
+                    student.name, other.name
+                ));
+            }
+        }
+    }
+
+    let names = |students: Vec<&Student>| {
+        students
+            .iter()
+            .map(|s| s.name.clone())
+            .collect::<Vec<String>>()
+            .join(", ")
+    };
+
+    let without_preferences: Vec<&Student> = students
+        .iter()
+        .filter(|s| s.preferences.is_empty() && s.locked.is_none())
+        .collect();
+    if !without_preferences.is_empty() {
+        warnings.push(format!(
+            "Leerlingen zonder voorkeuren worden willekeurig ingedeeld: {}",
+            names(without_preferences)
+        ));
+    }
+
+    let all_excluded: Vec<&Student> = students
+        .iter()
+        .filter(|s| {
+            s.locked.is_none()
+                && !categories.is_empty()
+                && categories
+                    .iter()
+                    .filter(|c| free_places(c, students, rounds) > 0)
+                    .all(|c| s.exclude.contains(&c.id) || !c.admits(s))
+        })
+        .collect();
+    if !all_excluded.is_empty() {
+        warnings.push(format!(
+            "Leerlingen die van alle activiteiten zijn uitgesloten en dus niet kunnen worden ingedeeld: {}",
+            names(all_excluded)
+        ));
+    }
+
+    let ineligible: Vec<String> = students
+        .iter()
+        .flat_map(|s| {
+            s.preferences
+                .iter()
+                .filter_map(|id| category_by_id(categories, *id))
+                .filter(move |c| !c.admits(s))
+                .map(move |c| format!("{} ({})", s.name, c.name))
+        })
+        .collect();
+    if !ineligible.is_empty() {
+        warnings.push(format!(
+            "Voorkeuren voor activiteiten waar de leerling niet aan mag meedoen worden overgeslagen: {}",
+            ineligible.join(", ")
+        ));
+    }
+
+    warnings
+}
+
+pub struct DemandBlock {
+    props: Props,
+    link: ComponentLink<Self>,
+    top: usize,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub categories: RefCell<Vec<Category>>,
+    pub students: RefCell<Vec<Student>>,
+    pub multi_matches: bool,
+    pub rounds: usize,
+}
+
+pub enum Msg {
+    SetTop(ChangeData),
+}
+
+impl Component for DemandBlock {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            top: 3,
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::SetTop(ChangeData::Value(value)) => {
+                if let Ok(top) = value.parse::<usize>() {
+                    self.top = top.max(1);
+                }
+                true
+            }
+            Msg::SetTop(_) => false,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let categories = self.props.categories.borrow();
+        let students = self.props.students.borrow();
+        let demand = category_demand(&students, &categories, self.top, self.props.rounds);
+        let warnings = feasibility_warnings(
+            &students,
+            &categories,
+            self.props.multi_matches,
+            self.props.rounds,
+        );
+
+        let handle_set_top = self.link.callback(Msg::SetTop);
+
+        html! {
+            <div class="row d-print-none">
+                <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <h2 class="mb-3">{ "Vraag naar activiteiten" }</h2>
+                    <p>{ "Bekijk voordat je de indeling maakt hoe populair de activiteiten zijn. Activiteiten die vaker als eerste keuze zijn gekozen dan er vrije plekken zijn, zijn oranje gemarkeerd." }</p>
+                    {
+                        warnings.iter().map(|warning| {
+                            html! { <div class="alert alert-warning">{ warning }</div> }
+                        }).collect::<Html>()
+                    }
+                    <form class="form-inline mb-2">
+                        <label class="mr-2" for="demand_top">{ "Aantal voorkeuren meetellen:" }</label>
+                        <input type="number" class="form-control form-control-sm" id="demand_top" min="1" value=self.top onchange=handle_set_top />
+                    </form>
+                    <table class="table table-sm table-responsive-sm">
+                        <tr>
+                            <th>{ "Activiteit" }</th>
+                            <th class="text-right">{ "Eerste keuze" }</th>
+                            <th class="text-right">{ format!("In top {}", self.top) }</th>
+                            <th class="text-right" title="De plekken in alle rondes waarin de activiteit plaatsvindt, zonder de plekken van vastgezette leerlingen.">{ "Vrije plekken" }</th>
+                        </tr>
+                        <tbody>
+                        {
+                            demand.iter().filter_map(|demand| Some((demand, category_by_id(&categories, demand.category_id)?))).map(|(demand, category)| {
+                                let row_class = if demand.first_choice > demand.free_places { "table-warning" } else { "" };
+                                html! {
+                                    <tr class=row_class>
+                                        <td>{ &category.name }</td>
+                                        <td class="text-right">{ demand.first_choice }</td>
+                                        <td class="text-right">{ demand.top_choices }</td>
+                                        <td class="text-right">{ demand.free_places }</td>
+                                    </tr>
+                                }
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(id: Id, max_placements: usize, slots: &[usize]) -> Category {
+        let mut category = Category::new(&format!("Activiteit {}", id), max_placements);
+        category.id = id;
+        category.slots = slots.to_vec();
+        category
+    }
+
+    fn student(id: Id, preferences: &[Id]) -> Student {
+        let mut student = Student::new(&format!("Leerling {}", id), preferences.to_vec(), vec![]);
+        student.id = id;
+        student
+    }
+
+    #[test]
+    fn free_places_count_every_round_and_leave_out_locked_students() {
+        let categories = vec![category(1, 2, &[0, 1]), category(2, 3, &[1])];
+        let mut students = vec![student(10, &[1]), student(11, &[1]), student(12, &[2])];
+        students[0].locked = Some(1);
+
+        let demand = category_demand(&students, &categories, 3, 2);
+        assert_eq!(demand[0].free_places, 3);
+        assert_eq!(demand[0].first_choice, 1);
+        assert_eq!(demand[1].free_places, 3);
+
+        let demand = category_demand(&students, &categories, 3, 1);
+        assert_eq!(demand[0].free_places, 1);
+    }
+
+    #[test]
+    fn activities_filled_by_locked_students_count_as_full() {
+        let categories = vec![category(1, 1, &[]), category(2, 5, &[])];
+        let mut students = vec![student(10, &[1]), student(11, &[1])];
+        students[0].locked = Some(1);
+        students[1].exclude = vec![2];
+
+        let warnings = feasibility_warnings(&students, &categories, false, 1);
+        assert!(warnings
+            .iter()
+            .any(|w| w.contains("uitgesloten") && w.contains("Leerling 11")));
+    }
+}
@@ -0,0 +1,199 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::model::{Category, Id, MatchResult, Student};
+use super::xlsx::{write_workbook, Cell, Sheet};
+use csv::WriterBuilder;
+
+pub const CSV_MIME_TYPE: &str = "text/csv;charset=utf-8";
+const NOT_PLACED: &str = "Niet ingedeeld";
+
+pub fn preference_rank(student: &Student, category_id: Id) -> Option<usize> {
+    student
+        .preferences
+        .iter()
+        .position(|c| *c == category_id)
+        .map(|index| index + 1)
+}
+
+// One (student, class, activity, rank) row per placement, followed by the
+// students that could not be placed.
+fn placement_rows(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+) -> Vec<(String, String, String, Option<usize>)> {
+    let mut rows = vec![];
+    for category in categories {
+        for student in placed_students(match_result, students, category.id) {
+            rows.push((
+                student.name.clone(),
+                student.class.clone(),
+                category.name.clone(),
+                preference_rank(student, category.id),
+            ));
+        }
+    }
+    for student in students
+        .iter()
+        .filter(|s| match_result.not_placable.contains(&s.id))
+    {
+        rows.push((
+            student.name.clone(),
+            student.class.clone(),
+            NOT_PLACED.to_string(),
+            None,
+        ));
+    }
+    rows
+}
+
+pub fn placed_students<'a>(
+    match_result: &MatchResult,
+    students: &'a [Student],
+    category_id: Id,
+) -> Vec<&'a Student> {
+    match_result
+        .placed
+        .get(&category_id)
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| students.iter().find(|s| s.id == *id))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Excel only recognises UTF-8 when the file starts with a byte order mark and
+// expects ';' as separator when running with a Dutch locale.
+pub fn match_result_csv(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+) -> Result<Vec<u8>, String> {
+    let mut writer = WriterBuilder::new()
+        .delimiter(b';')
+        .from_writer(b"\xEF\xBB\xBF".to_vec());
+
+    let mut write = |record: &[&str]| {
+        writer
+            .write_record(record)
+            .map_err(|e| format!("De export kon niet worden gemaakt: {}", e))
+    };
+
+    write(&["Leerling", "Klas", "Activiteit", "Voorkeur"])?;
+    for (student, class, category, rank) in placement_rows(match_result, students, categories) {
+        let rank = rank.map(|rank| rank.to_string()).unwrap_or_default();
+        write(&[&student, &class, &category, &rank])?;
+    }
+
+    writer
+        .into_inner()
+        .map_err(|e| format!("De export kon niet worden gemaakt: {}", e))
+}
+
+pub fn match_result_xlsx(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+) -> Result<Vec<u8>, String> {
+    let header = |names: &[&str]| -> Vec<Cell> {
+        names
+            .iter()
+            .map(|name| Cell::Text(name.to_string()))
+            .collect()
+    };
+    let rank_cell = |rank: Option<usize>| rank.map(Cell::Number).unwrap_or(Cell::Empty);
+
+    let mut overview = Sheet {
+        name: "Overzicht".into(),
+        rows: vec![header(&["Leerling", "Klas", "Activiteit", "Voorkeur"])],
+    };
+    for (student, class, category, rank) in placement_rows(match_result, students, categories) {
+        overview.rows.push(vec![
+            Cell::Text(student),
+            Cell::Text(class),
+            Cell::Text(category),
+            rank_cell(rank),
+        ]);
+    }
+
+    let mut sheets = vec![overview];
+    for category in categories {
+        let mut sheet = Sheet {
+            name: category.name.clone(),
+            rows: vec![header(&["Leerling", "Klas", "Voorkeur"])],
+        };
+        for student in placed_students(match_result, students, category.id) {
+            sheet.rows.push(vec![
+                Cell::Text(student.name.clone()),
+                Cell::Text(student.class.clone()),
+                rank_cell(preference_rank(student, category.id)),
+            ]);
+        }
+        sheets.push(sheet);
+    }
+
+    let mut not_placable = Sheet {
+        name: NOT_PLACED.into(),
+        rows: vec![header(&["Leerling", "Klas"])],
+    };
+    for student in students
+        .iter()
+        .filter(|s| match_result.not_placable.contains(&s.id))
+    {
+        not_placable.rows.push(vec![
+            Cell::Text(student.name.clone()),
+            Cell::Text(student.class.clone()),
+        ]);
+    }
+    sheets.push(not_placable);
+
+    write_workbook(&sheets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_has_a_bom_semicolons_and_quotes() {
+        let mut categories = vec![Category::new("Koken; bakken", 5)];
+        categories[0].id = 1;
+        let mut students = vec![
+            Student::new("Sam \"de Kok\"", vec![1], vec![]),
+            Student::new("Noor", vec![1], vec![]),
+        ];
+        students[0].id = 10;
+        students[0].class = "1A".to_string();
+        students[1].id = 11;
+        let match_result = MatchResult {
+            placed: vec![(1, vec![10])].into_iter().collect(),
+            not_placable: vec![11],
+            ..MatchResult::default()
+        };
+
+        let csv = match_result_csv(&match_result, &students, &categories).unwrap();
+        assert!(csv.starts_with(b"\xEF\xBB\xBF"));
+        assert_eq!(
+            String::from_utf8(csv[3..].to_vec()).unwrap(),
+            "Leerling;Klas;Activiteit;Voorkeur\n\
+             \"Sam \"\"de Kok\"\"\";1A;\"Koken; bakken\";1\n\
+             Noor;;Niet ingedeeld;\n"
+        );
+    }
+}
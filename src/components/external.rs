@@ -20,4 +20,5 @@ use wasm_bindgen::prelude::wasm_bindgen;
 #[wasm_bindgen]
 extern "C" {
     pub fn print_page();
+    pub fn download_file(filename: &str, mime_type: &str, content: &[u8]);
 }
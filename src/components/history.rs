@@ -0,0 +1,78 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::collections::VecDeque;
+
+const MAX_DEPTH: usize = 100;
+
+// Snapshots are the serialized `State`, which is also what ends up in storage.
+#[derive(Default)]
+pub struct History {
+    undo: VecDeque<String>,
+    redo: Vec<String>,
+    group: Option<String>,
+}
+
+impl History {
+    // Consecutive edits within the same group, like the many moves of a single
+    // drag, are undone in one step.
+    pub fn record(&mut self, before: String, group: Option<String>) {
+        self.redo.clear();
+        if group.is_some() && group == self.group {
+            return;
+        }
+        self.group = group;
+        self.undo.push_back(before);
+        if self.undo.len() > MAX_DEPTH {
+            self.undo.pop_front();
+        }
+    }
+
+    // Ends the current group, so the next edit is undone on its own even when
+    // it belongs to the same group, like a second drag of the same student.
+    pub fn end_group(&mut self) {
+        self.group = None;
+    }
+
+    pub fn undo(&mut self, current: String) -> Option<String> {
+        let previous = self.undo.pop_back()?;
+        self.redo.push(current);
+        self.group = None;
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: String) -> Option<String> {
+        let next = self.redo.pop()?;
+        self.undo.push_back(current);
+        self.group = None;
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group = None;
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use csv::{ReaderBuilder, Trim};
+
+pub struct Row {
+    pub line: u64,
+    pub fields: Vec<String>,
+}
+
+impl Row {
+    pub fn is_empty(&self) -> bool {
+        self.fields.iter().all(|field| field.is_empty())
+    }
+
+    pub fn field(&self, index: usize) -> &str {
+        self.fields.get(index).map(|f| f.as_str()).unwrap_or("")
+    }
+}
+
+pub fn read_rows(content: &str) -> Result<Vec<Row>, String> {
+    let content = content.trim_start_matches('\u{feff}');
+    let mut reader = ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(Trim::All)
+        .delimiter(detect_delimiter(content))
+        .from_reader(content.as_bytes());
+
+    let mut rows = vec![];
+    for record in reader.records() {
+        let record = record.map_err(|e| format!("Het bestand kon niet worden gelezen: {}", e))?;
+        rows.push(Row {
+            line: record.position().map(|p| p.line()).unwrap_or(0),
+            fields: record.iter().map(String::from).collect(),
+        });
+    }
+    Ok(rows)
+}
+
+// Spreadsheets exported with a Dutch locale use ';', pasted cells use tabs.
+fn detect_delimiter(content: &str) -> u8 {
+    let first_line = content.lines().next().unwrap_or("");
+    let mut delimiter = b',';
+    let mut max_count = 0;
+    for candidate in b",;\t" {
+        let count = first_line.matches(*candidate as char).count();
+        if count > max_count {
+            delimiter = *candidate;
+            max_count = count;
+        }
+    }
+    delimiter
+}
@@ -15,30 +15,125 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
-use matchmaker::{Category, MatchResult};
+use super::export::{match_result_csv, match_result_xlsx, placed_students, CSV_MIME_TYPE};
+use super::external;
+use super::matching::{
+    blocking_pairs, move_student, release_seat, split_groups, violated_separations,
+};
+use super::model::{
+    category_by_id, classes, student_by_id, Category, Id, MatchResult, QuotaAttribute, SlotResult,
+    Student, TieBreak,
+};
+use super::statistics_block::StatisticsBlock;
+use super::xlsx::XLSX_MIME_TYPE;
 use std::cell::RefCell;
+use std::collections::BTreeMap;
 use yew::prelude::*;
 
 pub struct MatchResultBlock {
     props: Props,
+    link: ComponentLink<Self>,
+    error: Option<String>,
+    dragging: Option<(Id, Option<Id>)>,
+    warnings: Vec<String>,
+    promotions: Vec<String>,
+    class_filter: Option<String>,
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
     pub match_result: RefCell<MatchResult>,
     pub categories: RefCell<Vec<Category>>,
+    pub students: RefCell<Vec<Student>>,
+    pub on_move_student: Callback<(Id, Option<Id>, Option<Id>)>,
+    pub on_release_seat: Callback<(Id, Id)>,
+    // The waitlist of every activity, worked out by the parent.
+    pub waitlists: BTreeMap<Id, Vec<Id>>,
+}
+
+pub enum Msg {
+    DownloadCsv,
+    DownloadXlsx,
+    StartDrag(Id, Option<Id>),
+    DragOver,
+    EndDrag,
+    Drop(Option<Id>),
+    SetClassFilter(ChangeData),
+    ReleaseSeat(Id, Id),
 }
 
 impl Component for MatchResultBlock {
-    type Message = ();
+    type Message = Msg;
     type Properties = Props;
 
-    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
-        Self { props }
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            error: None,
+            dragging: None,
+            warnings: vec![],
+            promotions: vec![],
+            class_filter: None,
+        }
     }
 
-    fn update(&mut self, _: Self::Message) -> ShouldRender {
-        true
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::DownloadCsv => {
+                match match_result_csv(
+                    &self.props.match_result.borrow(),
+                    &self.props.students.borrow(),
+                    &self.props.categories.borrow(),
+                ) {
+                    Ok(content) => {
+                        external::download_file("eerlijke-indeling.csv", CSV_MIME_TYPE, &content);
+                        self.error = None;
+                    }
+                    Err(error) => self.error = Some(error),
+                }
+                true
+            }
+            Msg::DownloadXlsx => {
+                match match_result_xlsx(
+                    &self.props.match_result.borrow(),
+                    &self.props.students.borrow(),
+                    &self.props.categories.borrow(),
+                ) {
+                    Ok(content) => {
+                        external::download_file("eerlijke-indeling.xlsx", XLSX_MIME_TYPE, &content);
+                        self.error = None;
+                    }
+                    Err(error) => self.error = Some(error),
+                }
+                true
+            }
+            Msg::StartDrag(student_id, from) => {
+                self.dragging = Some((student_id, from));
+                true
+            }
+            Msg::DragOver => false,
+            Msg::EndDrag => {
+                self.dragging = None;
+                true
+            }
+            Msg::Drop(to) => {
+                if let Some((student_id, from)) = self.dragging.take() {
+                    self.move_student(student_id, from, to);
+                }
+                true
+            }
+            Msg::SetClassFilter(ChangeData::Select(select)) => {
+                let class = select.value();
+                self.class_filter = if class.is_empty() { None } else { Some(class) };
+                true
+            }
+            Msg::SetClassFilter(_) => false,
+            Msg::ReleaseSeat(student_id, category_id) => {
+                self.release_seat(student_id, category_id);
+                true
+            }
+        }
     }
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
@@ -47,34 +142,143 @@ impl Component for MatchResultBlock {
     }
 
     fn view(&self) -> Html {
+        let match_result = self.props.match_result.borrow();
+        let students = self.props.students.borrow();
+        let classes = classes(&students);
+        let shown: Vec<Student> = students
+            .iter()
+            .filter(|student| {
+                self.class_filter
+                    .as_ref()
+                    .map(|class| *class == student.class)
+                    .unwrap_or(true)
+            })
+            .cloned()
+            .collect();
+        let handle_set_class_filter = self.link.callback(Msg::SetClassFilter);
+
+        let handle_download_csv = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::DownloadCsv
+        });
+        let handle_download_xlsx = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::DownloadXlsx
+        });
+        let handle_drag_over = self.link.callback(|e: DragEvent| {
+            e.prevent_default();
+            Msg::DragOver
+        });
+        let handle_drop_not_placed = self.link.callback(|e: DragEvent| {
+            e.prevent_default();
+            Msg::Drop(None)
+        });
+        let cancelled: BTreeMap<Id, usize> = match_result
+            .cancelled
+            .iter()
+            .map(|c| (c.category_id, c.participants))
+            .collect();
+
         html! {
             <>
                 <div class="row">
                     <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <div class="d-print-none">
+                        <button class="btn btn-info btn-sm float-right ml-2" onclick=handle_download_xlsx>{ "Download als Excel" }</button>
+                        <button class="btn btn-info btn-sm float-right" onclick=handle_download_csv>{ "Download als CSV" }</button>
+                        {
+                            if match_result.slots.is_empty() {
+                                html! { <p class="small text-muted">{ "Sleep een leerling naar een andere activiteit om de indeling met de hand aan te passen." }</p> }
+                            } else {
+                                html! { <p class="small text-muted">{ "Bij een indeling met rondes kun je leerlingen niet verslepen en geen plekken vrijgeven. Een leerling doet elke activiteit maar één keer, dus een wijziging in de ene ronde verandert ook wat er in de andere rondes kan. Pas daarom de voorkeuren aan en maak de indeling opnieuw." }</p> }
+                            }
+                        }
+                        {
+                            if let Some(error) = &self.error {
+                                html! { <div class="invalid-feedback d-block">{ error }</div> }
+                            } else {
+                                html! {}
+                            }
+                        }
+                        {
+                            self.warnings.iter().map(|warning| {
+                                html! { <div class="alert alert-warning">{ warning }</div> }
+                            }).collect::<Html>()
+                        }
+                        {
+                            self.promotions.iter().map(|promotion| {
+                                html! { <div class="alert alert-info">{ promotion }</div> }
+                            }).collect::<Html>()
+                        }
+                        {
+                            if !classes.is_empty() {
+                                html! {
+                                    <form class="form-inline mb-3">
+                                        <label class="mr-2" for="result_class_filter">{ "Toon klas" }</label>
+                                        <select class="form-control form-control-sm" id="result_class_filter" onchange=handle_set_class_filter>
+                                            <option value="" selected=self.class_filter.is_none()>{ "Alle klassen" }</option>
+                                            {
+                                                classes.iter().map(|class| {
+                                                    html! { <option value=class selected=self.class_filter.as_ref() == Some(class)>{ class }</option> }
+                                                }).collect::<Html>()
+                                            }
+                                        </select>
+                                    </form>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </div>
+                    {
+                        if let Some(class) = &self.class_filter {
+                            html! { <h3>{ format!("Klas {}", class) }</h3> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <p class="text-muted small mb-0">{ tie_break_description(match_result.tie_break) }</p>
+                    {
+                        if let Some(seed) = match_result.seed {
+                            html! { <p class="text-muted small mb-0" title="Met dit startgetal en dezelfde gegevens wordt precies dezelfde indeling gemaakt.">{ format!("Startgetal van de loting: {}", seed) }</p> }
+                        } else {
+                            html! {}
+                        }
+                    }
                     {
                         self.props.categories.borrow().chunks(4).map(|categories| {
                             html! {
                                 <div class="row">
                                 {
                                     categories.iter().map(|category| {
+                                        let category_id = category.id;
+                                        let handle_drag_over = self.link.callback(|e: DragEvent| {
+                                            e.prevent_default();
+                                            Msg::DragOver
+                                        });
+                                        let handle_drop = self.link.callback(move |e: DragEvent| {
+                                            e.prevent_default();
+                                            Msg::Drop(Some(category_id))
+                                        });
                                         html! {
-                                            <div class="col col-md-3 pt-3">
-                                                <h4>{ &category.name }</h4>
+                                            <div class="col col-md-3 pt-3" ondragover=handle_drag_over ondrop=handle_drop>
+                                                <h4>{ format!("{} ({}/{})", category.name, match_result.placed.get(&category.id).map(|s| s.len()).unwrap_or(0), category.total_places(match_result.slots.len())) }</h4>
                                                 {
-                                                    self.props.match_result.borrow().placed.get(&category.name).map(|students| {
-                                                        html! {
+                                                    match (cancelled.get(&category.id), placed_students(&match_result, &shown, category.id)) {
+                                                        (Some(participants), _) => html! {
+                                                            <p class="font-italic text-danger">{ format!("Gaat niet door: er konden maar {} leerlingen worden ingedeeld, terwijl er minimaal {} nodig zijn.", participants, category.min_placements) }</p>
+                                                        },
+                                                        (None, placed) if !placed.is_empty() => html! {
                                                             <ul class="list-group">
                                                             {
-                                                                students.iter().map(|student| {
-                                                                    html! { <li class="list-group-item">{ &student.name }</li> }
-                                                                }).collect::<Html>()
+                                                                placed.iter().map(|student| self.view_student(student, Some(category.id))).collect::<Html>()
                                                             }
                                                             </ul>
-                                                        }
-                                                    }).unwrap_or({
-                                                        html! {<p class="font-italic">{ "Geen leerlingen in deze activiteit" }</p>}
-                                                    })
+                                                        },
+                                                        (None, _) => html! {<p class="font-italic">{ "Geen leerlingen in deze activiteit" }</p>},
+                                                    }
                                                 }
+                                                { self.view_waitlist(&students, category.id) }
                                             </div>
                                         }
                                     }).collect::<Vec<Html>>()
@@ -88,15 +292,13 @@ impl Component for MatchResultBlock {
 
                 <div class="row">
                     {
-                        if !self.props.match_result.borrow().not_placable.is_empty() {
+                        if !match_result.not_placable.is_empty() || self.dragging.is_some() {
                             html! {
-                                <div class="col shadow p-3 mb-5 bg-white rounded">
+                                <div class="col shadow p-3 mb-5 bg-white rounded" ondragover=handle_drag_over ondrop=handle_drop_not_placed>
                                     <h3>{ "Niet ingedeelde leerlingen" }</h3>
                                     <ul class="list-group">
                                     {
-                                        self.props.match_result.borrow().not_placable.iter().map(|student| {
-                                            html! { <li class="list-group-item">{ &student.name }</li> }
-                                        }).collect::<Html>()
+                                        match_result.not_placable.iter().filter_map(|id| student_by_id(&shown, *id)).map(|student| self.view_student(student, None)).collect::<Html>()
                                     }
                                     </ul>
                                 </div>
@@ -106,7 +308,494 @@ impl Component for MatchResultBlock {
                         }
                     }
                 </div>
+
+                {
+                    if !match_result.released.is_empty() {
+                        html! {
+                            <div class="row">
+                                <div class="col shadow p-3 mb-5 bg-white rounded">
+                                    <h3>{ "Leerlingen die hun plek hebben vrijgegeven" }</h3>
+                                    <ul class="list-group">
+                                    {
+                                        match_result.released.iter().filter_map(|id| student_by_id(&shown, *id)).map(|student| {
+                                            html! { <li class="list-group-item">{ &student.name }</li> }
+                                        }).collect::<Html>()
+                                    }
+                                    </ul>
+                                </div>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+
+                {
+                    match split_groups(&match_result, &students) {
+                        groups if !groups.is_empty() => html! {
+                            <div class="row">
+                                <div class="col shadow p-3 mb-5 bg-white rounded">
+                                    <h3>{ "Groepjes die niet bij elkaar zijn ingedeeld" }</h3>
+                                    <ul class="list-group">
+                                    {
+                                        groups.iter().map(|members| {
+                                            let names = members.iter().filter_map(|id| student_by_id(&students, *id)).map(|s| s.name.clone()).collect::<Vec<String>>().join(", ");
+                                            html! { <li class="list-group-item">{ names }</li> }
+                                        }).collect::<Html>()
+                                    }
+                                    </ul>
+                                </div>
+                            </div>
+                        },
+                        _ => html! {},
+                    }
+                }
+
+                {
+                    match violated_separations(&match_result, &students) {
+                        pairs if !pairs.is_empty() => html! {
+                            <div class="row">
+                                <div class="col shadow p-3 mb-5 bg-white rounded">
+                                    <h3>{ "Leerlingen die ondanks 'Niet samen met' bij elkaar zijn ingedeeld" }</h3>
+                                    <ul class="list-group">
+                                    {
+                                        pairs.iter().map(|(a, b)| {
+                                            let name = |id: &Id| student_by_id(&students, *id).map(|s| s.name.clone()).unwrap_or_default();
+                                            html! { <li class="list-group-item">{ format!("{} en {}", name(a), name(b)) }</li> }
+                                        }).collect::<Html>()
+                                    }
+                                    </ul>
+                                </div>
+                            </div>
+                        },
+                        _ => html! {},
+                    }
+                }
+
+                {
+                    if !match_result.slots.is_empty() {
+                        self.view_timetable(&match_result, &shown)
+                    } else {
+                        html! {}
+                    }
+                }
+
+                {
+                    if !match_result.lottery.is_empty() {
+                        view_lottery(&match_result, &shown)
+                    } else {
+                        html! {}
+                    }
+                }
+
+                {
+                    if !classes.is_empty() || self.props.categories.borrow().iter().any(|c| c.quota_tag().is_some()) {
+                        self.view_composition(&match_result, &students, &classes)
+                    } else {
+                        html! {}
+                    }
+                }
+
+                <StatisticsBlock match_result=self.props.match_result.clone() categories=self.props.categories.clone() students=self.props.students.clone() />
             </>
         }
     }
 }
+
+// A column of the composition table.
+enum Group {
+    Class(String),
+    Tag(String),
+}
+
+impl MatchResultBlock {
+    // With rounds, a quota applies per round, so a class exceeds it when it
+    // has too many students in any of the rounds. A quota on a tag gets its
+    // own column with the students that have the tag.
+    fn view_composition(
+        &self,
+        match_result: &MatchResult,
+        students: &[Student],
+        classes: &[String],
+    ) -> Html {
+        let categories = self.props.categories.borrow();
+        let count = |placed: &BTreeMap<Id, Vec<Id>>, category_id: Id, group: &Group| {
+            placed
+                .get(&category_id)
+                .map(|ids| {
+                    ids.iter()
+                        .filter_map(|id| student_by_id(students, *id))
+                        .filter(|s| match group {
+                            Group::Class(class) => s.class == *class,
+                            Group::Tag(tag) => s.tags.contains(tag),
+                        })
+                        .count()
+                })
+                .unwrap_or(0)
+        };
+        let exceeds = |category: &Category, group: &Group| {
+            let limit = match (&category.quota, category.quota_limit(), group) {
+                (Some(quota), Some(limit), Group::Class(_))
+                    if quota.attribute == QuotaAttribute::Class =>
+                {
+                    limit
+                }
+                (Some(_), Some(limit), Group::Tag(_)) => limit,
+                _ => return false,
+            };
+            if match_result.slots.is_empty() {
+                count(&match_result.placed, category.id, group) > limit
+            } else {
+                match_result
+                    .slots
+                    .iter()
+                    .any(|slot| count(&slot.placed, category.id, group) > limit)
+            }
+        };
+        let has_tag_quota = categories.iter().any(|c| c.quota_tag().is_some());
+
+        html! {
+            <div class="row">
+                <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <h3>{ "Samenstelling per activiteit" }</h3>
+                    <table class="table table-sm table-responsive-sm">
+                        <tr>
+                            <th>{ "Activiteit" }</th>
+                            <th>{ "Max. aandeel" }</th>
+                            {
+                                classes.iter().map(|class| html! { <th class="text-right">{ class }</th> }).collect::<Html>()
+                            }
+                            {
+                                if has_tag_quota {
+                                    html! { <th class="text-right">{ "Met kenmerk" }</th> }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                        </tr>
+                        <tbody>
+                        {
+                            categories.iter().filter(|c| match_result.placed.contains_key(&c.id)).map(|category| {
+                                let quota = match (&category.quota, category.quota_limit()) {
+                                    (Some(quota), Some(limit)) => {
+                                        let attribute = match &quota.attribute {
+                                            QuotaAttribute::Class => "per klas".to_string(),
+                                            QuotaAttribute::Tag(tag) => format!("met {}", tag),
+                                        };
+                                        format!("{} {} ({}%, {})", limit, attribute, quota.percentage, if quota.hard { "hard" } else { "zacht" })
+                                    }
+                                    _ => "-".to_string(),
+                                };
+                                html! {
+                                    <tr>
+                                        <td>{ &category.name }</td>
+                                        <td>{ quota }</td>
+                                        {
+                                            classes.iter().map(|class| {
+                                                let group = Group::Class(class.clone());
+                                                let cell_class = if exceeds(category, &group) { "text-right table-warning" } else { "text-right" };
+                                                html! { <td class=cell_class>{ count(&match_result.placed, category.id, &group) }</td> }
+                                            }).collect::<Html>()
+                                        }
+                                        {
+                                            match category.quota_tag() {
+                                                Some(tag) => {
+                                                    let group = Group::Tag(tag.to_string());
+                                                    let cell_class = if exceeds(category, &group) { "text-right table-warning" } else { "text-right" };
+                                                    html! { <td class=cell_class>{ count(&match_result.placed, category.id, &group) }</td> }
+                                                }
+                                                None if has_tag_quota => html! { <td class="text-right">{ "-" }</td> },
+                                                None => html! {},
+                                            }
+                                        }
+                                    </tr>
+                                }
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_timetable(&self, match_result: &MatchResult, students: &[Student]) -> Html {
+        let categories = self.props.categories.borrow();
+        let activity = |slot: &SlotResult, student_id: Id| {
+            slot.placed
+                .iter()
+                .find(|(_, s)| s.contains(&student_id))
+                .and_then(|(category_id, _)| category_by_id(&categories, *category_id))
+                .map(|c| c.name.clone())
+                .unwrap_or_else(|| "Niet ingedeeld".to_string())
+        };
+
+        html! {
+            <div class="row">
+                <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <h3>{ "Rooster per leerling" }</h3>
+                    <table class="table table-sm table-responsive-sm">
+                        <tr>
+                            <th>{ "Leerling" }</th>
+                            {
+                                (1..=match_result.slots.len()).map(|round| {
+                                    html! { <th>{ format!("Ronde {}", round) }</th> }
+                                }).collect::<Html>()
+                            }
+                        </tr>
+                        <tbody>
+                        {
+                            students.iter().map(|student| {
+                                html! {
+                                    <tr>
+                                        <td>{ &student.name }</td>
+                                        {
+                                            match_result.slots.iter().map(|slot| {
+                                                html! { <td>{ activity(slot, student.id) }</td> }
+                                            }).collect::<Html>()
+                                        }
+                                    </tr>
+                                }
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                </div>
+            </div>
+        }
+    }
+
+    fn view_waitlist(&self, students: &[Student], category_id: Id) -> Html {
+        let waitlist = match self.props.waitlists.get(&category_id) {
+            Some(waitlist) => waitlist,
+            None => return html! {},
+        };
+
+        html! {
+            <div class="d-print-none mt-2">
+                <h6 title="Leerlingen die deze activiteit liever hebben dan hun huidige plek, op volgorde van de loting. Komt er een plek vrij, dan schuift de eerste door.">{ "Wachtlijst" }</h6>
+                <ol class="small pl-3">
+                {
+                    waitlist.iter().filter_map(|id| student_by_id(students, *id)).map(|student| {
+                        html! { <li>{ &student.name }</li> }
+                    }).collect::<Html>()
+                }
+                </ol>
+            </div>
+        }
+    }
+
+    fn view_student(&self, student: &Student, from: Option<Id>) -> Html {
+        let student_id = student.id;
+        let handle_drag_start = self
+            .link
+            .callback(move |_: DragEvent| Msg::StartDrag(student_id, from));
+        let handle_drag_end = self.link.callback(|_: DragEvent| Msg::EndDrag);
+        let draggable = self.props.match_result.borrow().slots.is_empty();
+        let style = if self.dragging == Some((student_id, from)) {
+            "opacity: 0.4;"
+        } else {
+            ""
+        };
+
+        html! {
+            <li class="list-group-item" style=style draggable=draggable ondragstart=handle_drag_start ondragend=handle_drag_end>
+                { &student.name }
+                {
+                    if !student.class.is_empty() && self.class_filter.is_none() {
+                        html! { <span class="text-muted small ml-1">{ format!("({})", student.class) }</span> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if from.is_some() && student.locked == from {
+                        html! { <span class="badge badge-secondary ml-2" title="Deze leerling is vooraf vastgezet bij deze activiteit.">{ "vast" }</span> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    match from {
+                        Some(category_id) if draggable => {
+                            let handle_release_seat = self.link.callback(move |e: MouseEvent| {
+                                e.prevent_default();
+                                Msg::ReleaseSeat(student_id, category_id)
+                            });
+                            html! { <button class="btn btn-link btn-sm float-right p-0 d-print-none" title="De leerling doet niet meer mee. De eerste leerling van de wachtlijst krijgt de plek." onclick=handle_release_seat>{ "Plek vrijgeven" }</button> }
+                        }
+                        _ => html! {},
+                    }
+                }
+            </li>
+        }
+    }
+
+    // The cascade is worked out on a copy first, so the teacher can see who
+    // moved up.
+    fn release_seat(&mut self, student_id: Id, category_id: Id) {
+        let students = self.props.students.borrow();
+        let categories = self.props.categories.borrow();
+        let mut after = self.props.match_result.borrow().clone();
+
+        let promotions =
+            match release_seat(&mut after, &students, &categories, student_id, category_id) {
+                Ok(promotions) => promotions,
+                Err(error) => {
+                    self.error = Some(error);
+                    return;
+                }
+            };
+
+        let name = |id| {
+            student_by_id(&students, id)
+                .map(|s| s.name.clone())
+                .unwrap_or_default()
+        };
+        let category_name = |id| {
+            category_by_id(&categories, id)
+                .map(|c| c.name.clone())
+                .unwrap_or_default()
+        };
+        self.promotions = promotions
+            .iter()
+            .map(|promotion| match promotion.from {
+                Some(from) => format!(
+                    "{} schuift door van {} naar {}.",
+                    name(promotion.student),
+                    category_name(from),
+                    category_name(promotion.to)
+                ),
+                None => format!(
+                    "{} is alsnog ingedeeld bij {}.",
+                    name(promotion.student),
+                    category_name(promotion.to)
+                ),
+            })
+            .collect();
+        if self.promotions.is_empty() {
+            self.promotions.push(format!(
+                "De plek van {} bij {} is vrijgekomen. Er staat niemand op de wachtlijst.",
+                name(student_id),
+                category_name(category_id)
+            ));
+        }
+        self.warnings.clear();
+        self.error = None;
+        self.props.on_release_seat.emit((student_id, category_id));
+    }
+
+    // The move is checked against a copy first, so the teacher can be warned
+    // about blocking pairs that did not exist before the move.
+    fn move_student(&mut self, student_id: Id, from: Option<Id>, to: Option<Id>) {
+        let students = self.props.students.borrow();
+        let categories = self.props.categories.borrow();
+        let before = self.props.match_result.borrow().clone();
+        let mut after = before.clone();
+
+        if let Err(error) = move_student(
+            &mut after,
+            &students,
+            &categories,
+            student_id,
+            None,
+            from,
+            to,
+        ) {
+            self.error = Some(error);
+            return;
+        }
+
+        let existing = blocking_pairs(&before, &students, &categories);
+        let name = |id| {
+            student_by_id(&students, id)
+                .map(|s| s.name.clone())
+                .unwrap_or_default()
+        };
+        self.warnings = blocking_pairs(&after, &students, &categories)
+            .into_iter()
+            .filter(|pair| !existing.contains(pair))
+            .map(|pair| {
+                format!(
+                    "{} wil liever naar {} dan naar de huidige plek, terwijl {} daar een plek heeft met een slechter lotnummer.",
+                    name(pair.student),
+                    category_by_id(&categories, pair.category).map(|c| c.name.as_str()).unwrap_or(""),
+                    name(pair.other)
+                )
+            })
+            .collect();
+        self.promotions.clear();
+        self.error = None;
+        self.props.on_move_student.emit((student_id, from, to));
+    }
+}
+
+fn tie_break_description(tie_break: TieBreak) -> &'static str {
+    match tie_break {
+        TieBreak::Single => "Indelingsmethode: Deferred Acceptance met één loting voor alle activiteiten (single tie-break)",
+        TieBreak::Multiple => "Indelingsmethode: Deferred Acceptance met een aparte loting per activiteit (multiple tie-break)",
+    }
+}
+
+fn view_lottery(match_result: &MatchResult, students: &[Student]) -> Html {
+    let with_priority = !match_result.priorities.is_empty();
+    let multiple = match_result.tie_break == TieBreak::Multiple;
+
+    html! {
+        <div class="row">
+            <div class="col shadow p-3 mb-5 bg-white rounded">
+                <h3>{ "Uitleg van de loting" }</h3>
+                <p>{ "Bij een activiteit met te weinig plekken gaan de leerlingen met het beste lotnummer voor." }</p>
+                {
+                    if multiple {
+                        html! { <p>{ "Voor iedere activiteit is apart geloot. De lotnummers hieronder komen uit de algemene loting, die alleen de volgorde bepaalde waarin leerlingen zonder plek willekeurig werden ingedeeld. De volgorde op de wachtlijst van een activiteit volgt uit de loting van die activiteit." }</p> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if with_priority {
+                        html! { <p>{ "Leerlingen met een hogere voorrang kregen een beter lotnummer dan alle leerlingen met een lagere voorrang. Binnen dezelfde voorrang is de volgorde geloot." }</p> }
+                    } else {
+                        html! {}
+                    }
+                }
+                <details>
+                    <summary>{ "Toon de lotnummers" }</summary>
+                    <table class="table table-sm table-responsive-sm mt-2">
+                        <tr>
+                            <th class="text-right">{ "Lotnummer" }</th>
+                            <th>{ "Leerling" }</th>
+                            {
+                                if with_priority {
+                                    html! { <th class="text-right">{ "Voorrang" }</th> }
+                                } else {
+                                    html! {}
+                                }
+                            }
+                        </tr>
+                        <tbody>
+                        {
+                            match_result.lottery.iter().enumerate().filter_map(|(position, id)| Some((position, student_by_id(students, *id)?))).map(|(position, student)| {
+                                html! {
+                                    <tr>
+                                        <td class="text-right">{ position + 1 }</td>
+                                        <td>{ &student.name }</td>
+                                        {
+                                            if with_priority {
+                                                html! { <td class="text-right">{ match_result.priorities.get(&student.id).copied().unwrap_or(0) }</td> }
+                                            } else {
+                                                html! {}
+                                            }
+                                        }
+                                    </tr>
+                                }
+                            }).collect::<Html>()
+                        }
+                        </tbody>
+                    </table>
+                </details>
+            </div>
+        </div>
+    }
+}
@@ -0,0 +1,2018 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::model::{
+    category_by_id, student_by_id, CancelledCategory, Category, Id, MatchResult, SlotResult,
+    Student, TieBreak,
+};
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rand_chacha::ChaCha20Rng;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+
+pub struct MatchOptions {
+    pub tie_break: TieBreak,
+    pub multi_matches: bool,
+    pub rounds: usize,
+    pub max_activities: Option<usize>,
+    pub seed: u64,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Promotion {
+    pub student: Id,
+    pub from: Option<Id>,
+    pub to: Id,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct BlockingPair {
+    pub student: Id,
+    pub category: Id,
+    pub other: Id,
+}
+
+// A group of students that is matched as a whole. Students that are not in
+// a group form a unit on their own.
+#[derive(Clone)]
+struct Unit {
+    members: Vec<Id>,
+    // Every member, in the same order as the members, for the quotas.
+    students: Vec<Student>,
+    preferences: Vec<Id>,
+    exclude: Vec<Id>,
+    separate: Vec<Id>,
+    // The maximum number of activities in multi match mode.
+    limit: usize,
+    priority: u32,
+}
+
+impl Unit {
+    fn conflicts_with(&self, student_ids: &[Id]) -> bool {
+        student_ids.iter().any(|id| self.separate.contains(id))
+    }
+
+    fn single(&self, index: usize) -> Unit {
+        Unit {
+            members: vec![self.members[index]],
+            students: vec![self.students[index].clone()],
+            ..self.clone()
+        }
+    }
+}
+
+// Activities with fewer participants than their minimum are cancelled one at
+// a time, starting with the least popular one, after which everyone is
+// matched again without it. The lotteries of all activities are drawn once
+// up front, so the students keep their positions when one is cancelled.
+// ChaCha20 is used instead of StdRng, because its output for a given seed
+// does not change between rand versions.
+pub fn make_matches(
+    students: &[Student],
+    categories: &[Category],
+    options: &MatchOptions,
+) -> MatchResult {
+    let mut rng = ChaCha20Rng::seed_from_u64(options.seed);
+    let lottery = Lottery::draw(
+        &units(students, categories, options.max_activities),
+        categories,
+        options.tie_break,
+        &mut rng,
+    );
+    let mut cancelled: Vec<CancelledCategory> = vec![];
+    loop {
+        let active: Vec<Category> = categories
+            .iter()
+            .filter(|c| !cancelled.iter().any(|cc| cc.category_id == c.id))
+            .cloned()
+            .collect();
+        let mut match_result = match_with_lottery(students, &active, options, &lottery, &rng);
+        let under_subscribed = active
+            .iter()
+            .filter(|c| c.min_placements > 0)
+            .map(|c| (c, participants(&match_result, c.id)))
+            .filter(|(c, participants)| *participants < c.min_placements)
+            .min_by_key(|(c, participants)| (*participants, c.id));
+        match under_subscribed {
+            Some((category, participants)) => cancelled.push(CancelledCategory {
+                category_id: category.id,
+                participants,
+            }),
+            None => {
+                match_result.cancelled = cancelled;
+                return match_result;
+            }
+        }
+    }
+}
+
+// With rounds, the minimum applies to every round in which the activity runs.
+fn participants(match_result: &MatchResult, category_id: Id) -> usize {
+    if match_result.slots.is_empty() {
+        match_result
+            .placed
+            .get(&category_id)
+            .map(|s| s.len())
+            .unwrap_or(0)
+    } else {
+        match_result
+            .slots
+            .iter()
+            .filter_map(|slot| slot.placed.get(&category_id).map(|s| s.len()))
+            .min()
+            .unwrap_or(0)
+    }
+}
+
+// Deferred Acceptance with the lotteries drawn by `make_matches`. The units
+// only differ in the activities they can choose when an activity is
+// cancelled, so their positions still apply. The random fill continues from
+// the state the generator had after the draw, the same for every attempt.
+fn match_with_lottery(
+    students: &[Student],
+    categories: &[Category],
+    options: &MatchOptions,
+    lottery: &Lottery,
+    rng: &ChaCha20Rng,
+) -> MatchResult {
+    let mut rng = rng.clone();
+    let units = units(students, categories, options.max_activities);
+    let members_of = |order: &[usize]| -> Vec<Id> {
+        order
+            .iter()
+            .flat_map(|u| units[*u].members.iter().copied())
+            .collect()
+    };
+    let locked: BTreeMap<Id, Id> = students
+        .iter()
+        .filter_map(|s| Some((s.id, s.locked?)))
+        .filter(|(_, category_id)| category_by_id(categories, *category_id).is_some())
+        .collect();
+
+    let mut match_result = MatchResult {
+        seed: Some(options.seed),
+        tie_break: options.tie_break,
+        lottery: members_of(&lottery.order),
+        category_lotteries: lottery
+            .category_orders
+            .iter()
+            .filter(|(category_id, _)| category_by_id(categories, **category_id).is_some())
+            .map(|(category_id, order)| (*category_id, members_of(order)))
+            .collect(),
+        // The tier the unit was drawn with, which for a group can be lower
+        // than the tier of a member.
+        priorities: units
+            .iter()
+            .filter(|unit| unit.priority > 0)
+            .flat_map(|unit| unit.members.iter().map(move |id| (*id, unit.priority)))
+            .collect(),
+        ..MatchResult::default()
+    };
+
+    if options.rounds > 1 {
+        schedule(
+            &mut match_result,
+            &units,
+            lottery,
+            &locked,
+            categories,
+            options.rounds,
+            &mut rng,
+        );
+    } else {
+        let slot = match_slot(
+            &units,
+            lottery,
+            &locked,
+            categories,
+            options.multi_matches,
+            &mut rng,
+        );
+        match_result.placed = slot.placed;
+        match_result.not_placable = slot.not_placable;
+    }
+
+    let positions = lottery_positions(&match_result.lottery);
+    let category_positions: BTreeMap<Id, BTreeMap<Id, usize>> = match_result
+        .category_lotteries
+        .iter()
+        .map(|(category_id, lottery)| (*category_id, lottery_positions(lottery)))
+        .collect();
+    for (category_id, student_ids) in match_result.placed.iter_mut().chain(
+        match_result
+            .slots
+            .iter_mut()
+            .flat_map(|s| s.placed.iter_mut()),
+    ) {
+        let positions = category_positions.get(category_id).unwrap_or(&positions);
+        student_ids.sort_by_key(|id| positions.get(id));
+    }
+
+    match_result
+}
+
+// Every round (time slot) is matched on its own with the activities that run
+// in it. Activities a student already got in an earlier round are excluded,
+// so nobody does the same activity twice. A locked student is placed in the
+// first round in which the activity runs.
+fn schedule(
+    match_result: &mut MatchResult,
+    units: &[Unit],
+    lottery: &Lottery,
+    locked: &BTreeMap<Id, Id>,
+    categories: &[Category],
+    rounds: usize,
+    rng: &mut ChaCha20Rng,
+) {
+    let mut taken: BTreeMap<Id, Vec<Id>> = BTreeMap::new();
+    for round in 0..rounds {
+        let round_categories: Vec<Category> = categories
+            .iter()
+            .filter(|c| c.runs_in(round))
+            .cloned()
+            .collect();
+        let round_units: Vec<Unit> = units
+            .iter()
+            .map(|unit| {
+                let mut unit = unit.clone();
+                for student_id in unit.members.iter() {
+                    unit.exclude
+                        .extend(taken.get(student_id).cloned().unwrap_or_default());
+                }
+                let exclude = unit.exclude.clone();
+                unit.preferences.retain(|c| !exclude.contains(c));
+                unit
+            })
+            .collect();
+        let round_locked: BTreeMap<Id, Id> = locked
+            .iter()
+            .filter(|(_, category_id)| {
+                category_by_id(categories, **category_id)
+                    .and_then(|c| (0..rounds).find(|r| c.runs_in(*r)))
+                    == Some(round)
+            })
+            .map(|(student_id, category_id)| (*student_id, *category_id))
+            .collect();
+
+        let slot = match_slot(
+            &round_units,
+            lottery,
+            &round_locked,
+            &round_categories,
+            false,
+            rng,
+        );
+        for (category_id, student_ids) in slot.placed.iter() {
+            for student_id in student_ids {
+                taken.entry(*student_id).or_default().push(*category_id);
+            }
+            match_result
+                .placed
+                .entry(*category_id)
+                .or_default()
+                .extend(student_ids.iter().copied());
+        }
+        for student_id in slot.not_placable.iter() {
+            if !match_result.not_placable.contains(student_id) {
+                match_result.not_placable.push(*student_id);
+            }
+        }
+        match_result.slots.push(slot);
+    }
+}
+
+fn match_slot(
+    units: &[Unit],
+    lottery: &Lottery,
+    locked: &BTreeMap<Id, Id>,
+    categories: &[Category],
+    multi_matches: bool,
+    rng: &mut ChaCha20Rng,
+) -> SlotResult {
+    let students: BTreeMap<Id, &Student> = units
+        .iter()
+        .flat_map(|unit| unit.students.iter())
+        .map(|s| (s.id, s))
+        .collect();
+    let mut placed: BTreeMap<Id, Vec<Id>> = categories.iter().map(|c| (c.id, vec![])).collect();
+    for (student_id, category_id) in locked.iter() {
+        if let Some(locked) = placed.get_mut(category_id) {
+            locked.push(*student_id);
+        }
+    }
+
+    // Locked students only take part in the matching for their other
+    // activities, which is only possible in multi match mode.
+    let mut remaining: Vec<Vec<Id>> = units
+        .iter()
+        .map(|unit| {
+            let locked = unit.members.iter().find_map(|id| locked.get(id));
+            if locked.is_some() && !multi_matches {
+                vec![]
+            } else {
+                unit.preferences
+                    .iter()
+                    .filter(|c| placed.contains_key(c) && locked != Some(*c))
+                    .copied()
+                    .collect()
+            }
+        })
+        .collect();
+
+    // In multi match mode every round places a unit in at most one more
+    // activity, and only the units with the fewest placements take part. So
+    // everyone gets a first activity before anyone gets a second one, and a
+    // second one before anyone gets a third. A unit that gets nothing in its
+    // round never will, because places only run out.
+    let mut count: Vec<usize> = units
+        .iter()
+        .map(|unit| {
+            unit.members
+                .iter()
+                .filter(|id| locked.contains_key(id))
+                .count()
+        })
+        .collect();
+    loop {
+        let active: Vec<usize> = (0..units.len())
+            .filter(|u| !remaining[*u].is_empty() && count[*u] < units[*u].limit)
+            .collect();
+        let level = match active.iter().map(|u| count[*u]).min() {
+            Some(level) => level,
+            None => break,
+        };
+        let applying: Vec<usize> = active.into_iter().filter(|u| count[*u] == level).collect();
+        let preferences: Vec<Vec<Id>> = (0..units.len())
+            .map(|u| {
+                if applying.contains(&u) {
+                    remaining[u].clone()
+                } else {
+                    vec![]
+                }
+            })
+            .collect();
+
+        let capacity = free_capacity(&placed, categories);
+        let round = deferred_acceptance(
+            units,
+            &preferences,
+            &placed,
+            &capacity,
+            categories,
+            &students,
+            lottery,
+        );
+        let mut got_placed = vec![false; units.len()];
+        for (category_id, unit_indices) in round {
+            for u in unit_indices {
+                got_placed[u] = true;
+                count[u] += 1;
+                remaining[u].retain(|c| *c != category_id);
+                placed
+                    .entry(category_id)
+                    .or_default()
+                    .extend(units[u].members.iter().copied());
+            }
+        }
+        for u in applying {
+            if !got_placed[u] {
+                remaining[u].clear();
+            }
+        }
+        if !multi_matches {
+            break;
+        }
+    }
+
+    // Units without any placement are placed in a random activity that still
+    // has room, in lottery order. When no activity has room for a whole
+    // group, its members are placed on their own.
+    let mut not_placable = vec![];
+    for u in lottery.order.iter() {
+        let unit = &units[*u];
+        if placed.values().any(|s| s.contains(&unit.members[0])) {
+            continue;
+        }
+        if let Some(category_id) = random_category(&placed, categories, &students, unit, rng) {
+            placed
+                .entry(category_id)
+                .or_default()
+                .extend(unit.members.iter().copied());
+            continue;
+        }
+        for (index, student_id) in unit.members.iter().enumerate() {
+            match random_category(&placed, categories, &students, &unit.single(index), rng) {
+                Some(category_id) => placed.entry(category_id).or_default().push(*student_id),
+                None => not_placable.push(*student_id),
+            }
+        }
+    }
+
+    SlotResult {
+        placed,
+        not_placable,
+    }
+}
+
+// Students of a group share a unit with a combined preference list: the
+// activities are ordered by the sum of the ranks the members gave them, and
+// activities excluded by any member or that do not admit a member are left
+// out. A group only gets the priority tier all its members have. Locked
+// students are always matched on their own.
+fn units(
+    students: &[Student],
+    categories: &[Category],
+    max_activities: Option<usize>,
+) -> Vec<Unit> {
+    let mut groups: BTreeMap<u32, Vec<&Student>> = BTreeMap::new();
+    for student in students.iter().filter(|s| s.locked.is_none()) {
+        if let Some(group) = student.group {
+            groups.entry(group).or_default().push(student);
+        }
+    }
+    groups.retain(|_, members| members.len() > 1);
+
+    let mut units = vec![];
+    for student in students {
+        let group = student.group.filter(|_| student.locked.is_none());
+        let members = match group.and_then(|g| groups.get(&g)) {
+            Some(members) if members[0].id == student.id => members.clone(),
+            Some(_) => continue,
+            None => vec![student],
+        };
+
+        let ineligible = categories
+            .iter()
+            .filter(|c| members.iter().any(|s| !c.admits(s)))
+            .map(|c| c.id);
+        let exclude: Vec<Id> = members
+            .iter()
+            .flat_map(|s| s.exclude.clone())
+            .chain(ineligible)
+            .collect();
+        let mut preferences: Vec<Id> = vec![];
+        for category_id in members.iter().flat_map(|s| s.preferences.iter()) {
+            if !preferences.contains(category_id) && !exclude.contains(category_id) {
+                preferences.push(*category_id);
+            }
+        }
+        let score = |category_id: &Id| -> usize {
+            members
+                .iter()
+                .map(|s| {
+                    s.preferences
+                        .iter()
+                        .position(|c| c == category_id)
+                        .unwrap_or(s.preferences.len())
+                })
+                .sum()
+        };
+        preferences.sort_by_key(score);
+
+        units.push(Unit {
+            members: members.iter().map(|s| s.id).collect(),
+            students: members.iter().map(|s| (*s).clone()).collect(),
+            preferences,
+            exclude,
+            separate: members.iter().flat_map(|s| s.separate.clone()).collect(),
+            limit: members
+                .iter()
+                .filter_map(|s| s.max_activities.or(max_activities))
+                .min()
+                .unwrap_or(usize::MAX),
+            priority: members.iter().map(|s| s.priority).min().unwrap_or(0),
+        });
+    }
+    units
+}
+
+// Activities where the unit stays within the quota are preferred. A
+// soft quota is only exceeded when no such activity is left.
+fn random_category(
+    placed: &BTreeMap<Id, Vec<Id>>,
+    categories: &[Category],
+    students: &BTreeMap<Id, &Student>,
+    unit: &Unit,
+    rng: &mut ChaCha20Rng,
+) -> Option<Id> {
+    let options: Vec<(Id, &[Id])> = free_capacity(placed, categories)
+        .iter()
+        .map(|(id, free)| {
+            (
+                *id,
+                *free,
+                placed.get(id).map(|s| s.as_slice()).unwrap_or(&[]),
+            )
+        })
+        .filter(|(id, free, placed)| {
+            *free >= unit.members.len()
+                && !unit.exclude.contains(id)
+                && !unit.conflicts_with(placed)
+        })
+        .map(|(id, _, placed)| (id, placed))
+        .collect();
+    let within_quota = |(id, placed): &&(Id, &[Id])| {
+        category_by_id(categories, *id)
+            .map(|c| fits_quota(c, placed, students, unit))
+            .unwrap_or(true)
+    };
+    let preferred: Vec<Id> = options
+        .iter()
+        .filter(within_quota)
+        .map(|(id, _)| *id)
+        .collect();
+    if !preferred.is_empty() {
+        return preferred.choose(rng).copied();
+    }
+    let allowed: Vec<Id> = options
+        .iter()
+        .filter(|(id, _)| {
+            category_by_id(categories, *id)
+                .and_then(|c| c.quota.as_ref())
+                .map(|quota| !quota.hard)
+                .unwrap_or(true)
+        })
+        .map(|(id, _)| *id)
+        .collect();
+    allowed.choose(rng).copied()
+}
+
+// Whether the unit can be added to the students already placed in the
+// activity without a class, or the students with the tag, exceeding the
+// quota.
+fn fits_quota(
+    category: &Category,
+    placed: &[Id],
+    students: &BTreeMap<Id, &Student>,
+    unit: &Unit,
+) -> bool {
+    let (quota, limit) = match (category.quota.as_ref(), category.quota_limit()) {
+        (Some(quota), Some(limit)) => (quota, limit),
+        _ => return true,
+    };
+    unit.students
+        .iter()
+        .filter_map(|s| quota.group(s))
+        .all(|group| {
+            let in_group = |s: &Student| quota.group(s) == Some(group);
+            let placed_count = placed
+                .iter()
+                .filter_map(|id| students.get(id))
+                .filter(|s| in_group(s))
+                .count();
+            placed_count + unit.students.iter().filter(|s| in_group(s)).count() <= limit
+        })
+}
+
+// The order of the units in the general lottery and, with multiple
+// tie-breaks, the position of every unit in the lottery of each activity.
+struct Lottery {
+    order: Vec<usize>,
+    ranks: Vec<usize>,
+    category_orders: BTreeMap<Id, Vec<usize>>,
+    category_ranks: BTreeMap<Id, Vec<usize>>,
+}
+
+impl Lottery {
+    // With a single tie-break every unit draws one lottery position, which
+    // decides between all units applying for the same activity. With
+    // multiple tie-breaks every activity holds a lottery of its own, the
+    // general lottery then only decides the order of the random fill. Units
+    // with a higher priority tier are moved to the front of every lottery,
+    // the random order is kept within a tier.
+    fn draw(
+        units: &[Unit],
+        categories: &[Category],
+        tie_break: TieBreak,
+        rng: &mut ChaCha20Rng,
+    ) -> Self {
+        let mut draw = || {
+            let mut order: Vec<usize> = (0..units.len()).collect();
+            order.shuffle(rng);
+            order.sort_by_key(|u| Reverse(units[*u].priority));
+            order
+        };
+        let order = draw();
+        let category_orders: BTreeMap<Id, Vec<usize>> = match tie_break {
+            TieBreak::Single => BTreeMap::new(),
+            TieBreak::Multiple => categories.iter().map(|c| (c.id, draw())).collect(),
+        };
+        Lottery::new(order, category_orders)
+    }
+
+    fn new(order: Vec<usize>, category_orders: BTreeMap<Id, Vec<usize>>) -> Self {
+        let ranks = |order: &[usize]| {
+            let mut ranks = vec![0; order.len()];
+            for (position, u) in order.iter().enumerate() {
+                ranks[*u] = position;
+            }
+            ranks
+        };
+        Lottery {
+            ranks: ranks(&order),
+            category_ranks: category_orders
+                .iter()
+                .map(|(category_id, order)| (*category_id, ranks(order)))
+                .collect(),
+            category_orders,
+            order,
+        }
+    }
+
+    fn rank(&self, category_id: Id, unit: usize) -> usize {
+        self.category_ranks.get(&category_id).unwrap_or(&self.ranks)[unit]
+    }
+}
+
+fn lottery_positions(lottery: &[Id]) -> BTreeMap<Id, usize> {
+    lottery
+        .iter()
+        .enumerate()
+        .map(|(position, id)| (*id, position))
+        .collect()
+}
+
+fn free_capacity(placed: &BTreeMap<Id, Vec<Id>>, categories: &[Category]) -> BTreeMap<Id, usize> {
+    categories
+        .iter()
+        .map(|c| {
+            let taken = placed.get(&c.id).map(|s| s.len()).unwrap_or(0);
+            (c.id, c.max_placements.saturating_sub(taken))
+        })
+        .collect()
+}
+
+// Units apply to their preferences in order. An activity tentatively holds
+// the applicants with the best lottery positions and rejects the rest, who
+// then apply to their next preference. A group that does not fit in an
+// activity at all is rejected right away. Of two units that may not be placed
+// together, the one with the worse lottery position is rejected.
+fn deferred_acceptance(
+    units: &[Unit],
+    preferences: &[Vec<Id>],
+    placed: &BTreeMap<Id, Vec<Id>>,
+    capacity: &BTreeMap<Id, usize>,
+    categories: &[Category],
+    students: &BTreeMap<Id, &Student>,
+    lottery: &Lottery,
+) -> BTreeMap<Id, Vec<usize>> {
+    let mut held: BTreeMap<Id, Vec<usize>> = BTreeMap::new();
+    let mut next_choice = vec![0; units.len()];
+    let mut applicants: Vec<usize> = lottery.order.iter().rev().copied().collect();
+
+    while let Some(u) = applicants.pop() {
+        let category_id = match preferences[u].get(next_choice[u]) {
+            Some(category_id) => *category_id,
+            None => continue,
+        };
+        next_choice[u] += 1;
+
+        let available = capacity.get(&category_id).copied().unwrap_or(0);
+        let already_placed = placed
+            .get(&category_id)
+            .map(|s| s.as_slice())
+            .unwrap_or(&[]);
+        if units[u].members.len() > available || units[u].conflicts_with(already_placed) {
+            applicants.push(u);
+            continue;
+        }
+
+        let holding = held.entry(category_id).or_default();
+        let conflicting: Vec<usize> = holding
+            .iter()
+            .copied()
+            .filter(|v| units[u].conflicts_with(&units[*v].members))
+            .collect();
+        let rank = |u: usize| lottery.rank(category_id, u);
+        if conflicting.iter().any(|v| rank(*v) < rank(u)) {
+            applicants.push(u);
+            continue;
+        }
+        holding.retain(|v| !conflicting.contains(v));
+        applicants.extend(conflicting);
+        holding.push(u);
+        holding.sort_by_key(|u| rank(*u));
+        let kept = keep(
+            holding,
+            units,
+            category_by_id(categories, category_id),
+            available,
+            already_placed,
+            students,
+        );
+        applicants.extend(holding.iter().filter(|v| !kept.contains(v)));
+        *holding = kept;
+    }
+
+    held
+}
+
+// An activity keeps the units with the best lottery positions that fit. With
+// a quota, units that would exceed it are passed over, and with a soft
+// quota they only get the places that are left after that.
+fn keep(
+    holding: &[usize],
+    units: &[Unit],
+    category: Option<&Category>,
+    available: usize,
+    already_placed: &[Id],
+    students: &BTreeMap<Id, &Student>,
+) -> Vec<usize> {
+    let mut kept: Vec<usize> = vec![];
+    let mut members: Vec<Id> = already_placed.to_vec();
+    let mut free = available;
+    let hard = category
+        .and_then(|c| c.quota.as_ref())
+        .map(|quota| quota.hard)
+        .unwrap_or(false);
+
+    for within_quota in [true, false].iter() {
+        if !within_quota && hard {
+            break;
+        }
+        for u in holding {
+            let unit = &units[*u];
+            if kept.contains(u) || unit.members.len() > free {
+                continue;
+            }
+            if *within_quota
+                && !category
+                    .map(|c| fits_quota(c, &members, students, unit))
+                    .unwrap_or(true)
+            {
+                continue;
+            }
+            kept.push(*u);
+            members.extend(unit.members.iter().copied());
+            free -= unit.members.len();
+        }
+    }
+    kept.sort_by_key(|u| holding.iter().position(|v| v == u));
+    kept
+}
+
+// A group is kept together when all members ended up in exactly the same
+// activities.
+pub fn split_groups(match_result: &MatchResult, students: &[Student]) -> Vec<Vec<Id>> {
+    let placements = |id: &Id| {
+        match_result
+            .placed
+            .iter()
+            .filter(|(_, s)| s.contains(id))
+            .map(|(category_id, _)| *category_id)
+            .collect::<Vec<Id>>()
+    };
+    let mut groups: BTreeMap<u32, Vec<Id>> = BTreeMap::new();
+    for student in students {
+        if let Some(group) = student.group {
+            groups.entry(group).or_default().push(student.id);
+        }
+    }
+    groups
+        .into_values()
+        .filter(|members| {
+            members.len() > 1
+                && (placements(&members[0]).is_empty()
+                    || members
+                        .iter()
+                        .any(|id| placements(id) != placements(&members[0])))
+        })
+        .collect()
+}
+
+// Pairs of students that are placed in the same activity although they may
+// not be together, for instance because both were locked to it. With rounds
+// they are only together when they are in the same activity in the same
+// round.
+pub fn violated_separations(match_result: &MatchResult, students: &[Student]) -> Vec<(Id, Id)> {
+    let mut pairs = vec![];
+    for student in students {
+        for other in student.separate.iter().filter(|id| **id > student.id) {
+            if round_placements(match_result)
+                .iter()
+                .flat_map(|placed| placed.values())
+                .any(|s| s.contains(&student.id) && s.contains(other))
+            {
+                pairs.push((student.id, *other));
+            }
+        }
+    }
+    pairs
+}
+
+// With rounds every round is a matching of its own, otherwise there is only
+// the one.
+fn round_placements(match_result: &MatchResult) -> Vec<&BTreeMap<Id, Vec<Id>>> {
+    if match_result.slots.is_empty() {
+        vec![&match_result.placed]
+    } else {
+        match_result.slots.iter().map(|s| &s.placed).collect()
+    }
+}
+
+// The students placed in the activity, in the given round when matching with
+// rounds.
+fn placed_in(match_result: &MatchResult, slot: Option<usize>, category_id: Id) -> &[Id] {
+    slot.and_then(|slot| match_result.slots.get(slot))
+        .map(|s| &s.placed)
+        .unwrap_or(&match_result.placed)
+        .get(&category_id)
+        .map(|s| s.as_slice())
+        .unwrap_or(&[])
+}
+
+// With rounds a student is moved within a single round, where the capacity
+// of an activity applies. The placements of all rounds together are updated
+// along with it.
+pub fn move_student(
+    match_result: &mut MatchResult,
+    students: &[Student],
+    categories: &[Category],
+    student_id: Id,
+    slot: Option<usize>,
+    from: Option<Id>,
+    to: Option<Id>,
+) -> Result<(), String> {
+    if from == to {
+        return Ok(());
+    }
+    let student = student_by_id(students, student_id).ok_or("Onbekende leerling.")?;
+    let slot = match slot {
+        Some(slot) if slot < match_result.slots.len() => Some(slot),
+        None if match_result.slots.is_empty() => None,
+        _ => return Err("Een leerling kan alleen binnen een ronde worden verplaatst.".to_string()),
+    };
+
+    if let Some(to) = to {
+        let category = category_by_id(categories, to).ok_or("Onbekende activiteit.")?;
+        if let Some(slot) = slot.filter(|slot| !category.runs_in(*slot)) {
+            return Err(format!("{} is niet in ronde {}.", category.name, slot + 1));
+        }
+        check_join(match_result, students, category, student, slot)?;
+        if placed_in(match_result, slot, to).len() >= category.max_placements {
+            return Err(format!("{} is al vol.", category.name));
+        }
+    }
+
+    match slot {
+        Some(slot) => {
+            let slot = &mut match_result.slots[slot];
+            move_between(
+                &mut slot.placed,
+                &mut slot.not_placable,
+                student_id,
+                from,
+                to,
+            );
+            move_between(&mut match_result.placed, &mut vec![], student_id, from, to);
+            let not_placable = match_result
+                .slots
+                .iter()
+                .any(|s| s.not_placable.contains(&student_id));
+            match_result.not_placable.retain(|id| *id != student_id);
+            if not_placable {
+                match_result.not_placable.push(student_id);
+            }
+        }
+        None => move_between(
+            &mut match_result.placed,
+            &mut match_result.not_placable,
+            student_id,
+            from,
+            to,
+        ),
+    }
+
+    Ok(())
+}
+
+fn move_between(
+    placed: &mut BTreeMap<Id, Vec<Id>>,
+    not_placable: &mut Vec<Id>,
+    student_id: Id,
+    from: Option<Id>,
+    to: Option<Id>,
+) {
+    match from {
+        Some(from) => {
+            if let Some(placed) = placed.get_mut(&from) {
+                placed.retain(|id| *id != student_id);
+            }
+        }
+        None => not_placable.retain(|id| *id != student_id),
+    }
+
+    match to {
+        Some(to) => {
+            placed.entry(to).or_default().push(student_id);
+            not_placable.retain(|id| *id != student_id);
+        }
+        None => {
+            if !placed.values().any(|s| s.contains(&student_id))
+                && !not_placable.contains(&student_id)
+            {
+                not_placable.push(student_id);
+            }
+        }
+    }
+}
+
+// Whether the student may be placed in the activity, apart from whether
+// there is room. With rounds the quota and separations apply to the
+// students in the same round, and nobody does the same activity twice.
+fn check_join(
+    match_result: &MatchResult,
+    students: &[Student],
+    category: &Category,
+    student: &Student,
+    slot: Option<usize>,
+) -> Result<(), String> {
+    let placed = placed_in(match_result, slot, category.id);
+    if match_result
+        .cancelled
+        .iter()
+        .any(|c| c.category_id == category.id)
+    {
+        return Err(format!("{} gaat niet door.", category.name));
+    }
+    if student.exclude.contains(&category.id) {
+        return Err(format!(
+            "{} is uitgesloten van {}.",
+            student.name, category.name
+        ));
+    }
+    if !category.admits(student) {
+        return Err(format!(
+            "{} mag niet meedoen aan {}.",
+            student.name, category.name
+        ));
+    }
+    if placed_in(match_result, None, category.id).contains(&student.id) {
+        return Err(format!(
+            "{} is al ingedeeld bij {}.",
+            student.name, category.name
+        ));
+    }
+    let hard_quota = category.quota.as_ref().filter(|quota| quota.hard);
+    if let (Some(quota), Some(limit)) = (hard_quota, category.quota_limit()) {
+        if let Some(group) = quota.group(student) {
+            let same_group = placed
+                .iter()
+                .filter_map(|id| student_by_id(students, *id))
+                .filter(|s| quota.group(s) == Some(group))
+                .count();
+            if same_group >= limit {
+                return Err(format!(
+                    "{} heeft al {} leerlingen {}, meer mag niet.",
+                    category.name,
+                    same_group,
+                    quota.describe_group(group)
+                ));
+            }
+        }
+    }
+    if let Some(other) = placed.iter().find(|id| student.separate.contains(id)) {
+        return Err(format!(
+            "{} mag niet samen met {} worden ingedeeld.",
+            student.name,
+            student_by_id(students, *other)
+                .map(|s| s.name.as_str())
+                .unwrap_or("")
+        ));
+    }
+    Ok(())
+}
+
+// The placement the student likes least, with its rank. Activities outside
+// the preferences rank last.
+fn worst_placement(placed: &BTreeMap<Id, Vec<Id>>, student: &Student) -> Option<(Id, usize)> {
+    placed
+        .iter()
+        .filter(|(_, s)| s.contains(&student.id))
+        .map(|(category_id, _)| {
+            let rank = student
+                .preferences
+                .iter()
+                .position(|c| c == category_id)
+                .unwrap_or(usize::MAX);
+            (*category_id, rank)
+        })
+        .max_by_key(|(_, rank)| *rank)
+}
+
+// The students that would rather have a seat in the activity than their
+// current (worst) placement, in lottery order. Students locked to their
+// placement and students that released their seat are not on a waitlist.
+pub fn waitlist(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+    category_id: Id,
+) -> Vec<Id> {
+    let category = match category_by_id(categories, category_id) {
+        Some(category) => category,
+        None => return vec![],
+    };
+    match_result
+        .lottery_for(category_id)
+        .iter()
+        .filter(|id| !match_result.released.contains(id))
+        .filter_map(|id| student_by_id(students, *id))
+        .filter(|student| {
+            let rank = match student.preferences.iter().position(|c| *c == category_id) {
+                Some(rank) => rank,
+                None => return false,
+            };
+            match worst_placement(&match_result.placed, student) {
+                Some((worst, _)) if student.locked == Some(worst) => false,
+                Some((_, worst_rank)) => rank < worst_rank,
+                None => true,
+            }
+        })
+        .filter(|student| check_join(match_result, students, category, student, None).is_ok())
+        .map(|student| student.id)
+        .collect()
+}
+
+// The waitlist of every activity. With rounds there are no waitlists, as
+// seats can not be released then.
+pub fn waitlists(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+) -> BTreeMap<Id, Vec<Id>> {
+    if !match_result.slots.is_empty() {
+        return BTreeMap::new();
+    }
+    categories
+        .iter()
+        .map(|c| (c.id, waitlist(match_result, students, categories, c.id)))
+        .filter(|(_, waitlist)| !waitlist.is_empty())
+        .collect()
+}
+
+// The student gives up their seat and is taken off all waitlists. The first
+// student on the waitlist moves up into the free seat, which frees the seat
+// they leave behind for the next waitlist, until a waitlist is empty or the
+// student moving up was not placed before.
+pub fn release_seat(
+    match_result: &mut MatchResult,
+    students: &[Student],
+    categories: &[Category],
+    student_id: Id,
+    category_id: Id,
+) -> Result<Vec<Promotion>, String> {
+    if !match_result.slots.is_empty() {
+        return Err("Bij een indeling met rondes kan geen plek worden vrijgegeven.".to_string());
+    }
+    match match_result.placed.get_mut(&category_id) {
+        Some(placed) if placed.contains(&student_id) => placed.retain(|id| *id != student_id),
+        _ => return Err("Deze leerling is niet bij deze activiteit ingedeeld.".to_string()),
+    }
+    if !match_result.released.contains(&student_id) {
+        match_result.released.push(student_id);
+    }
+
+    let mut promotions = vec![];
+    let mut free = Some(category_id);
+    while let Some(category_id) = free {
+        let next = match waitlist(match_result, students, categories, category_id).first() {
+            Some(next) => *next,
+            None => break,
+        };
+        let from = student_by_id(students, next)
+            .and_then(|student| worst_placement(&match_result.placed, student))
+            .map(|(from, _)| from);
+        if move_student(
+            match_result,
+            students,
+            categories,
+            next,
+            None,
+            from,
+            Some(category_id),
+        )
+        .is_err()
+        {
+            break;
+        }
+        promotions.push(Promotion {
+            student: next,
+            from,
+            to: category_id,
+        });
+        free = from;
+    }
+    Ok(promotions)
+}
+
+// A student forms a blocking pair with an activity when they prefer it to
+// one of their current placements, while another student with a worse
+// lottery position holds a seat there. Locked seats are not won by lottery,
+// so they never form a blocking pair, and a student locked to the placement
+// they like least does not compete for other seats. With rounds every round
+// is checked on its own, leaving out the activities the student already
+// does in another round. Activities the student may not join are skipped.
+pub fn blocking_pairs(
+    match_result: &MatchResult,
+    students: &[Student],
+    categories: &[Category],
+) -> Vec<BlockingPair> {
+    let mut pairs = vec![];
+
+    for (student, round) in students
+        .iter()
+        .filter(|s| match_result.lottery.contains(&s.id) && !match_result.released.contains(&s.id))
+        .flat_map(|s| {
+            round_placements(match_result)
+                .into_iter()
+                .map(move |r| (s, r))
+        })
+    {
+        let worst_rank = match worst_placement(round, student) {
+            Some((worst, _)) if student.locked == Some(worst) => continue,
+            Some((_, rank)) => rank,
+            None => usize::MAX,
+        };
+
+        for category_id in student.preferences.iter().take(worst_rank) {
+            let placed = match round.get(category_id) {
+                Some(placed) => placed,
+                None => continue,
+            };
+            let admitted = category_by_id(categories, *category_id)
+                .map(|c| c.admits(student))
+                .unwrap_or(false);
+            if !admitted
+                || placed_in(match_result, None, *category_id).contains(&student.id)
+                || student.exclude.contains(category_id)
+                || placed.iter().any(|id| student.separate.contains(id))
+            {
+                continue;
+            }
+            let positions = lottery_positions(match_result.lottery_for(*category_id));
+            for other in placed.iter() {
+                let locked = student_by_id(students, *other)
+                    .map(|s| s.locked == Some(*category_id))
+                    .unwrap_or(false);
+                if !locked && positions.get(other) > positions.get(&student.id) {
+                    pairs.push(BlockingPair {
+                        student: student.id,
+                        category: *category_id,
+                        other: *other,
+                    });
+                }
+            }
+        }
+    }
+
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::model::{Quota, QuotaAttribute};
+
+    fn category(id: Id, max_placements: usize) -> Category {
+        let mut category = Category::new(&format!("Activiteit {}", id), max_placements);
+        category.id = id;
+        category
+    }
+
+    fn student(id: Id, preferences: &[Id]) -> Student {
+        let mut student = Student::new(&format!("Leerling {}", id), preferences.to_vec(), vec![]);
+        student.id = id;
+        student
+    }
+
+    fn options(seed: u64) -> MatchOptions {
+        MatchOptions {
+            tie_break: TieBreak::Single,
+            multi_matches: false,
+            rounds: 1,
+            max_activities: None,
+            seed,
+        }
+    }
+
+    fn placements(match_result: &MatchResult, student_id: Id) -> Vec<Id> {
+        match_result
+            .placed
+            .iter()
+            .filter(|(_, s)| s.contains(&student_id))
+            .map(|(category_id, _)| *category_id)
+            .collect()
+    }
+
+    // Twelve seats for fifteen students, who all prefer the activities in a
+    // different order.
+    fn crowded() -> (Vec<Student>, Vec<Category>) {
+        let categories = vec![category(1, 4), category(2, 4), category(3, 4)];
+        let students = (0..15)
+            .map(|i| student(10 + i, &[1 + i % 3, 1 + (i + 1) % 3, 1 + (i + 2) % 3]))
+            .collect();
+        (students, categories)
+    }
+
+    #[test]
+    fn matches_are_stable() {
+        let (students, categories) = crowded();
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+            for student in students.iter() {
+                assert!(placements(&match_result, student.id).len() <= 1);
+            }
+        }
+    }
+
+    #[test]
+    fn capacity_is_respected() {
+        let (students, categories) = crowded();
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            for category in categories.iter() {
+                assert_eq!(match_result.placed[&category.id].len(), 4);
+            }
+            assert_eq!(match_result.not_placable.len(), 3);
+        }
+    }
+
+    #[test]
+    fn excluded_activities_are_never_assigned() {
+        let categories = vec![category(1, 10), category(2, 10)];
+        let mut students: Vec<Student> = (0..6).map(|i| student(10 + i, &[1])).collect();
+        for student in students.iter_mut().take(3) {
+            student.exclude = vec![1];
+        }
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            for student in students.iter() {
+                let expected = if student.exclude.is_empty() { 1 } else { 2 };
+                assert_eq!(placements(&match_result, student.id), vec![expected]);
+            }
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_result() {
+        let (students, categories) = crowded();
+        let first = make_matches(&students, &categories, &options(42));
+        let second = make_matches(&students, &categories, &options(42));
+        assert_eq!(first.placed, second.placed);
+        assert_eq!(first.not_placable, second.not_placable);
+        assert_eq!(first.lottery, second.lottery);
+        assert_eq!(first.seed, Some(42));
+
+        let other = make_matches(&students, &categories, &options(43));
+        assert_ne!(first.lottery, other.lottery);
+    }
+
+    #[test]
+    fn moving_a_student_checks_capacity_and_exclusions() {
+        let (mut students, categories) = crowded();
+        students[0].exclude = vec![3];
+        let mut match_result = make_matches(&students, &categories, &options(1));
+        let from = placements(&match_result, 10).first().copied();
+
+        assert!(move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            10,
+            None,
+            from,
+            Some(3)
+        )
+        .is_err());
+        let full = if from == Some(1) { 2 } else { 1 };
+        assert!(move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            10,
+            None,
+            from,
+            Some(full)
+        )
+        .is_err());
+        assert_eq!(
+            placements(&match_result, 10),
+            from.into_iter().collect::<Vec<Id>>()
+        );
+
+        move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            10,
+            None,
+            from,
+            None,
+        )
+        .unwrap();
+        assert!(placements(&match_result, 10).is_empty());
+        assert!(match_result.not_placable.contains(&10));
+    }
+
+    #[test]
+    fn giving_away_a_seat_won_in_the_lottery_creates_a_blocking_pair() {
+        let categories = vec![category(1, 1)];
+        let students = vec![student(10, &[1]), student(11, &[1])];
+        let mut match_result = make_matches(&students, &categories, &options(1));
+        let winner = match_result.placed[&1][0];
+        let loser = match_result.not_placable[0];
+        assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+
+        move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            winner,
+            None,
+            Some(1),
+            None,
+        )
+        .unwrap();
+        move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            loser,
+            None,
+            None,
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(
+            blocking_pairs(&match_result, &students, &categories),
+            vec![BlockingPair {
+                student: winner,
+                category: 1,
+                other: loser,
+            }]
+        );
+    }
+
+    #[test]
+    fn locked_students_keep_their_seat() {
+        let categories = vec![category(1, 2), category(2, 10)];
+        let mut students: Vec<Student> = (0..6).map(|i| student(10 + i, &[1, 2])).collect();
+        students[5].preferences = vec![2];
+        students[5].locked = Some(1);
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            assert_eq!(placements(&match_result, 15), vec![1]);
+            assert_eq!(match_result.placed[&1].len(), 2);
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+        }
+    }
+
+    #[test]
+    fn groups_are_placed_together() {
+        let categories = vec![category(1, 2), category(2, 3), category(3, 10)];
+        let mut students: Vec<Student> = (0..8).map(|i| student(10 + i, &[1, 2, 3])).collect();
+        for student in students.iter_mut().take(3) {
+            student.group = Some(1);
+        }
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let together = placements(&match_result, 10);
+            assert_eq!(together.len(), 1);
+            assert_ne!(together, vec![1]);
+            assert_eq!(placements(&match_result, 11), together);
+            assert_eq!(placements(&match_result, 12), together);
+            assert!(split_groups(&match_result, &students).is_empty());
+        }
+    }
+
+    #[test]
+    fn groups_that_fit_nowhere_are_split() {
+        let categories = vec![category(1, 2), category(2, 2)];
+        let mut students: Vec<Student> = (0..3).map(|i| student(10 + i, &[1, 2])).collect();
+        for student in students.iter_mut() {
+            student.group = Some(1);
+        }
+        let match_result = make_matches(&students, &categories, &options(1));
+        assert!(match_result.not_placable.is_empty());
+        assert_eq!(
+            split_groups(&match_result, &students),
+            vec![vec![10, 11, 12]]
+        );
+    }
+
+    #[test]
+    fn separated_students_are_never_placed_together() {
+        let categories = vec![category(1, 10), category(2, 10)];
+        let mut students: Vec<Student> = (0..4).map(|i| student(10 + i, &[1, 2])).collect();
+        students[0].separate = vec![11];
+        students[1].separate = vec![10];
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            assert_eq!(placements(&match_result, 10).len(), 1);
+            assert_eq!(placements(&match_result, 11).len(), 1);
+            assert_ne!(placements(&match_result, 10), placements(&match_result, 11));
+            assert!(violated_separations(&match_result, &students).is_empty());
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+        }
+    }
+
+    #[test]
+    fn separated_students_locked_together_are_reported() {
+        let categories = vec![category(1, 10)];
+        let mut students: Vec<Student> = (0..2).map(|i| student(10 + i, &[1])).collect();
+        students[0].separate = vec![11];
+        students[1].separate = vec![10];
+        for student in students.iter_mut() {
+            student.locked = Some(1);
+        }
+        let match_result = make_matches(&students, &categories, &options(1));
+        assert_eq!(
+            violated_separations(&match_result, &students),
+            vec![(10, 11)]
+        );
+    }
+
+    // A result with two rounds, built by hand. The placements of both rounds
+    // together are derived from the rounds.
+    fn with_rounds(slots: Vec<Vec<(Id, Vec<Id>)>>, lottery: Vec<Id>) -> MatchResult {
+        let mut match_result = MatchResult {
+            lottery,
+            ..MatchResult::default()
+        };
+        for placed in slots {
+            let placed: BTreeMap<Id, Vec<Id>> = placed.into_iter().collect();
+            for (category_id, student_ids) in placed.iter() {
+                match_result
+                    .placed
+                    .entry(*category_id)
+                    .or_default()
+                    .extend(student_ids.iter().copied());
+            }
+            match_result.slots.push(SlotResult {
+                placed,
+                not_placable: vec![],
+            });
+        }
+        match_result
+    }
+
+    #[test]
+    fn rounds_give_one_new_activity_per_round() {
+        let (students, categories) = crowded();
+        let options = MatchOptions {
+            rounds: 3,
+            ..options(0)
+        };
+        let students = &students[..12];
+        for seed in 0..20 {
+            let match_result =
+                make_matches(students, &categories, &MatchOptions { seed, ..options });
+            assert_eq!(match_result.slots.len(), 3);
+            for student in students.iter() {
+                let mut activities: Vec<Id> = match_result
+                    .slots
+                    .iter()
+                    .flat_map(|slot| {
+                        slot.placed
+                            .iter()
+                            .filter(|(_, s)| s.contains(&student.id))
+                            .map(|(category_id, _)| *category_id)
+                    })
+                    .collect();
+                activities.sort_unstable();
+                assert_eq!(activities, vec![1, 2, 3]);
+            }
+            for slot in match_result.slots.iter() {
+                assert!(slot.placed.values().all(|s| s.len() <= 4));
+            }
+            assert!(blocking_pairs(&match_result, students, &categories).is_empty());
+        }
+    }
+
+    #[test]
+    fn separations_only_apply_within_a_round() {
+        let mut students = vec![student(10, &[1, 2]), student(11, &[1, 2])];
+        students[0].separate = vec![11];
+        students[1].separate = vec![10];
+        let match_result = with_rounds(
+            vec![
+                vec![(1, vec![10]), (2, vec![11])],
+                vec![(1, vec![11]), (2, vec![10])],
+            ],
+            vec![10, 11],
+        );
+        assert!(violated_separations(&match_result, &students).is_empty());
+
+        let match_result = with_rounds(
+            vec![vec![(1, vec![10, 11])], vec![(2, vec![10, 11])]],
+            vec![10, 11],
+        );
+        assert_eq!(
+            violated_separations(&match_result, &students),
+            vec![(10, 11)]
+        );
+    }
+
+    #[test]
+    fn blocking_pairs_are_found_per_round() {
+        let students = vec![student(10, &[1, 2, 3]), student(11, &[1, 2, 3])];
+        let categories = vec![category(1, 1), category(2, 1), category(3, 1)];
+        // Student 10 did not get activity 2 in the first round, but is happy
+        // there with activity 1, so the seat of student 11 is not envied.
+        let match_result = with_rounds(
+            vec![
+                vec![(1, vec![10]), (2, vec![11])],
+                vec![(1, vec![11]), (3, vec![10])],
+            ],
+            vec![10, 11],
+        );
+        assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+
+        let match_result = with_rounds(
+            vec![
+                vec![(1, vec![11]), (2, vec![10])],
+                vec![(1, vec![]), (3, vec![10])],
+            ],
+            vec![10, 11],
+        );
+        assert_eq!(
+            blocking_pairs(&match_result, &students, &categories),
+            vec![BlockingPair {
+                student: 10,
+                category: 1,
+                other: 11,
+            }]
+        );
+    }
+
+    #[test]
+    fn students_are_moved_within_a_round() {
+        let categories = vec![category(1, 2), category(2, 2), category(3, 2)];
+        let students: Vec<Student> = (0..4).map(|i| student(10 + i, &[1, 2, 3])).collect();
+        let mut match_result = with_rounds(
+            vec![
+                vec![(1, vec![10, 11]), (2, vec![12]), (3, vec![13])],
+                vec![(1, vec![12]), (2, vec![13]), (3, vec![10, 11])],
+            ],
+            vec![10, 11, 12, 13],
+        );
+
+        assert!(move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            13,
+            None,
+            Some(2),
+            Some(1)
+        )
+        .is_err());
+        assert!(move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            12,
+            Some(0),
+            Some(2),
+            Some(1)
+        )
+        .is_err());
+        assert!(move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            10,
+            Some(1),
+            Some(3),
+            Some(1)
+        )
+        .is_err());
+        move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            13,
+            Some(1),
+            Some(2),
+            Some(1),
+        )
+        .unwrap();
+        assert_eq!(match_result.slots[1].placed[&1], vec![12, 13]);
+        assert_eq!(match_result.placed[&1], vec![10, 11, 12, 13]);
+        assert_eq!(match_result.placed[&2], vec![12]);
+
+        move_student(
+            &mut match_result,
+            &students,
+            &categories,
+            12,
+            Some(1),
+            Some(1),
+            None,
+        )
+        .unwrap();
+        assert_eq!(match_result.slots[1].not_placable, vec![12]);
+        assert_eq!(match_result.not_placable, vec![12]);
+    }
+
+    #[test]
+    fn under_subscribed_activities_are_cancelled() {
+        let mut categories = vec![category(1, 5), category(2, 10), category(3, 5)];
+        categories[0].min_placements = 3;
+        categories[2].min_placements = 2;
+        let mut students: Vec<Student> = (0..2).map(|i| student(10 + i, &[1, 2])).collect();
+        students.extend((2..6).map(|i| student(10 + i, &[2, 1])));
+        students.extend((6..8).map(|i| student(10 + i, &[3, 2])));
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let cancelled: Vec<(Id, usize)> = match_result
+                .cancelled
+                .iter()
+                .map(|c| (c.category_id, c.participants))
+                .collect();
+            assert_eq!(cancelled, vec![(1, 2)]);
+            assert!(!match_result.placed.contains_key(&1));
+            assert_eq!(match_result.placed[&2].len(), 6);
+            assert_eq!(match_result.placed[&3].len(), 2);
+            assert!(match_result.not_placable.is_empty());
+        }
+    }
+
+    #[test]
+    fn cancelled_students_can_save_another_activity() {
+        let mut categories = vec![category(1, 5), category(2, 5), category(3, 10)];
+        categories[0].min_placements = 2;
+        categories[1].min_placements = 2;
+        // Both activities start with one participant. Activity 1 goes first,
+        // after which its student moves to activity 2, which then runs.
+        let students = vec![
+            student(10, &[1, 2]),
+            student(11, &[2, 3]),
+            student(12, &[3]),
+            student(13, &[3]),
+        ];
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let cancelled: Vec<Id> = match_result
+                .cancelled
+                .iter()
+                .map(|c| c.category_id)
+                .collect();
+            assert_eq!(cancelled, vec![1]);
+            let mut placed = match_result.placed[&2].clone();
+            placed.sort_unstable();
+            assert_eq!(placed, vec![10, 11]);
+        }
+    }
+
+    fn with_quota(mut category: Category, attribute: QuotaAttribute, hard: bool) -> Category {
+        category.quota = Some(Quota {
+            attribute,
+            percentage: 50,
+            hard,
+        });
+        category
+    }
+
+    fn in_class(mut student: Student, class: &str) -> Student {
+        student.class = class.to_string();
+        student
+    }
+
+    #[test]
+    fn hard_class_quota_is_never_exceeded() {
+        let categories = vec![
+            with_quota(category(1, 4), QuotaAttribute::Class, true),
+            category(2, 10),
+        ];
+        let students: Vec<Student> = (0..8)
+            .map(|i| in_class(student(10 + i, &[1, 2]), if i < 6 { "A" } else { "B" }))
+            .collect();
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let class_a = match_result.placed[&1]
+                .iter()
+                .filter(|id| student_by_id(&students, **id).unwrap().class == "A")
+                .count();
+            assert_eq!(class_a, 2);
+            assert_eq!(match_result.placed[&1].len(), 4);
+            assert!(match_result.not_placable.is_empty());
+        }
+    }
+
+    #[test]
+    fn soft_quota_is_only_exceeded_to_fill_seats() {
+        let students: Vec<Student> = (0..6)
+            .map(|i| in_class(student(10 + i, &[1]), "A"))
+            .collect();
+        for hard in [true, false].iter() {
+            let categories = vec![with_quota(category(1, 4), QuotaAttribute::Class, *hard)];
+            for seed in 0..20 {
+                let match_result = make_matches(&students, &categories, &options(seed));
+                let expected = if *hard { 2 } else { 4 };
+                assert_eq!(match_result.placed[&1].len(), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn tag_quota_limits_students_with_the_tag() {
+        let categories = vec![
+            with_quota(
+                category(1, 6),
+                QuotaAttribute::Tag("sport".to_string()),
+                true,
+            ),
+            category(2, 10),
+        ];
+        let students: Vec<Student> = (0..5)
+            .map(|i| {
+                let mut student = in_class(student(10 + i, &[1, 2]), "A");
+                if i < 4 {
+                    student.tags = vec!["sport".to_string()];
+                }
+                student
+            })
+            .collect();
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let with_tag = match_result.placed[&1]
+                .iter()
+                .filter(|id| !student_by_id(&students, **id).unwrap().tags.is_empty())
+                .count();
+            assert_eq!(with_tag, 3);
+            assert_eq!(match_result.placed[&1].len(), 4);
+
+            // The activity still has room, but not for another student with
+            // the tag.
+            let outside = student_by_id(&students, match_result.placed[&2][0]).unwrap();
+            assert!(check_join(&match_result, &students, &categories[0], outside, None).is_err());
+        }
+    }
+
+    #[test]
+    fn priority_students_win_contested_seats() {
+        let categories = vec![category(1, 2), category(2, 10)];
+        let mut students: Vec<Student> = (0..6).map(|i| student(10 + i, &[1, 2])).collect();
+        students[3].priority = 1;
+        students[5].priority = 2;
+        for tie_break in [TieBreak::Single, TieBreak::Multiple].iter() {
+            for seed in 0..20 {
+                let options = MatchOptions {
+                    tie_break: *tie_break,
+                    ..options(seed)
+                };
+                let match_result = make_matches(&students, &categories, &options);
+                let mut placed = match_result.placed[&1].clone();
+                placed.sort_unstable();
+                assert_eq!(placed, vec![13, 15]);
+                assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn higher_tiers_come_first_in_the_lottery() {
+        let (mut students, categories) = crowded();
+        students[4].priority = 1;
+        students[7].priority = 1;
+        students[9].priority = 2;
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            assert_eq!(match_result.lottery[0], 19);
+            let mut tier_one = match_result.lottery[1..3].to_vec();
+            tier_one.sort_unstable();
+            assert_eq!(tier_one, vec![14, 17]);
+            assert_eq!(match_result.priorities.len(), 3);
+        }
+    }
+
+    #[test]
+    fn groups_only_get_the_tier_all_members_have() {
+        let categories = vec![category(1, 2), category(2, 10)];
+        let mut students: Vec<Student> = (0..6).map(|i| student(10 + i, &[1, 2])).collect();
+        students[0].group = Some(1);
+        students[0].priority = 1;
+        students[1].group = Some(1);
+        students[2].priority = 1;
+        students[3].priority = 1;
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            let mut placed = match_result.placed[&1].clone();
+            placed.sort_unstable();
+            assert_eq!(placed, vec![12, 13]);
+            assert_eq!(
+                match_result.priorities,
+                vec![(12, 1), (13, 1)].into_iter().collect()
+            );
+        }
+    }
+
+    #[test]
+    fn students_are_only_placed_where_they_are_admitted() {
+        let mut categories = vec![category(1, 10), category(2, 10), category(3, 10)];
+        categories[0].classes = vec!["A".to_string()];
+        categories[1].required_tags = vec!["sport".to_string()];
+        let students: Vec<Student> = (0..8)
+            .map(|i| {
+                let mut student = in_class(
+                    student(10 + i, &[1, 2, 3]),
+                    if i % 2 == 0 { "A" } else { "B" },
+                );
+                if i % 4 < 2 {
+                    student.tags = vec!["sport".to_string()];
+                }
+                student
+            })
+            .collect();
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &options(seed));
+            for student in students.iter() {
+                let expected = categories.iter().find(|c| c.admits(student)).unwrap().id;
+                assert_eq!(placements(&match_result, student.id), vec![expected]);
+            }
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+        }
+    }
+
+    #[test]
+    fn activities_a_student_may_not_join_form_no_blocking_pair() {
+        let mut categories = vec![category(1, 1), category(2, 1)];
+        categories[0].classes = vec!["A".to_string()];
+        let mut students = vec![
+            in_class(student(10, &[1, 2]), "B"),
+            in_class(student(11, &[1, 2]), "A"),
+        ];
+        let match_result = with_rounds(vec![vec![(1, vec![11]), (2, vec![10])]], vec![10, 11]);
+        assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+        assert!(check_join(
+            &match_result,
+            &students,
+            &categories[0],
+            &students[0],
+            Some(0)
+        )
+        .is_err());
+
+        students[0].class = "A".to_string();
+        assert_eq!(
+            blocking_pairs(&match_result, &students, &categories),
+            vec![BlockingPair {
+                student: 10,
+                category: 1,
+                other: 11,
+            }]
+        );
+    }
+
+    #[test]
+    fn released_seats_move_the_waitlists_up() {
+        let categories = vec![category(1, 1), category(2, 1), category(3, 1)];
+        let students: Vec<Student> = (0..4).map(|i| student(10 + i, &[1, 2, 3])).collect();
+        let mut match_result = MatchResult {
+            placed: vec![(1, vec![10]), (2, vec![11]), (3, vec![12])]
+                .into_iter()
+                .collect(),
+            not_placable: vec![13],
+            lottery: vec![10, 11, 12, 13],
+            ..MatchResult::default()
+        };
+        assert_eq!(
+            waitlists(&match_result, &students, &categories),
+            vec![(1, vec![11, 12, 13]), (2, vec![12, 13]), (3, vec![13])]
+                .into_iter()
+                .collect()
+        );
+
+        let promotions = release_seat(&mut match_result, &students, &categories, 10, 1).unwrap();
+        assert_eq!(
+            promotions,
+            vec![
+                Promotion {
+                    student: 11,
+                    from: Some(2),
+                    to: 1,
+                },
+                Promotion {
+                    student: 12,
+                    from: Some(3),
+                    to: 2,
+                },
+                Promotion {
+                    student: 13,
+                    from: None,
+                    to: 3,
+                },
+            ]
+        );
+        assert_eq!(match_result.released, vec![10]);
+        assert!(match_result.not_placable.is_empty());
+        assert!(placements(&match_result, 10).is_empty());
+        assert_eq!(
+            waitlists(&match_result, &students, &categories),
+            vec![(1, vec![12, 13]), (2, vec![13])].into_iter().collect()
+        );
+        assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+    }
+
+    #[test]
+    fn seats_can_not_be_released_with_rounds() {
+        let categories = vec![category(1, 1), category(2, 1)];
+        let students = vec![student(10, &[1, 2]), student(11, &[1, 2])];
+        let mut match_result = with_rounds(
+            vec![
+                vec![(1, vec![10]), (2, vec![11])],
+                vec![(1, vec![11]), (2, vec![10])],
+            ],
+            vec![10, 11],
+        );
+        assert!(waitlists(&match_result, &students, &categories).is_empty());
+        assert!(release_seat(&mut match_result, &students, &categories, 10, 1).is_err());
+        assert_eq!(match_result.placed[&1], vec![10, 11]);
+    }
+
+    fn multiple(seed: u64) -> MatchOptions {
+        MatchOptions {
+            tie_break: TieBreak::Multiple,
+            ..options(seed)
+        }
+    }
+
+    #[test]
+    fn multiple_tie_breaks_give_every_activity_its_own_lottery() {
+        let (students, categories) = crowded();
+        let mut differs = false;
+        for seed in 0..20 {
+            let match_result = make_matches(&students, &categories, &multiple(seed));
+            assert_eq!(match_result.category_lotteries.len(), 3);
+            for lottery in match_result.category_lotteries.values() {
+                let mut ids = lottery.clone();
+                ids.sort_unstable();
+                assert_eq!(ids, (10..25).collect::<Vec<Id>>());
+                differs |= *lottery != match_result.lottery;
+            }
+            for category in categories.iter() {
+                assert_eq!(match_result.placed[&category.id].len(), 4);
+            }
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+            assert_eq!(
+                make_matches(&students, &categories, &multiple(seed)).placed,
+                match_result.placed
+            );
+        }
+        assert!(differs);
+    }
+
+    #[test]
+    fn cancelling_an_activity_keeps_the_lottery_positions() {
+        // The activity that is cancelled comes first, so its lottery is drawn
+        // before the others.
+        let mut categories = vec![category(3, 10), category(1, 2), category(2, 2)];
+        let mut students: Vec<Student> = (0..6)
+            .map(|i| {
+                let mut student = student(10 + i, &[1, 2]);
+                student.exclude = vec![3];
+                student
+            })
+            .collect();
+        students.push(student(16, &[3, 1, 2]));
+        for tie_break in [TieBreak::Single, TieBreak::Multiple].iter() {
+            let options = MatchOptions {
+                tie_break: *tie_break,
+                ..options(7)
+            };
+            categories[0].min_placements = 0;
+            let kept = make_matches(&students, &categories, &options);
+            categories[0].min_placements = 2;
+            let match_result = make_matches(&students, &categories, &options);
+
+            assert_eq!(match_result.cancelled.len(), 1);
+            assert_eq!(match_result.lottery, kept.lottery);
+            for category_id in [1, 2].iter() {
+                assert_eq!(
+                    match_result.category_lotteries.get(category_id),
+                    kept.category_lotteries.get(category_id)
+                );
+            }
+            assert!(!match_result.category_lotteries.contains_key(&3));
+            assert!(blocking_pairs(&match_result, &students, &categories).is_empty());
+        }
+    }
+}
@@ -15,38 +15,63 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
+mod carry_over_block;
 mod category_block;
+mod category_import;
 mod category_row;
+mod demand_block;
+mod export;
 mod external;
+mod history;
+mod import;
 mod match_result_block;
+mod matching;
 mod modal;
+mod model;
+mod project_file;
+mod project_manager;
+mod projects;
+mod statistics_block;
 mod student_block;
+mod student_import;
 mod student_row;
+mod xlsx;
 
+use carry_over_block::CarryOverBlock;
 use category_block::CategoryBlock;
+use demand_block::DemandBlock;
+use history::History;
 use match_result_block::MatchResultBlock;
-use matchmaker::{
-    da_stb::{match_students, match_students_to_multiple_categories},
-    Category, MatchResult, Student,
-};
+use matching::MatchOptions;
 use modal::Modal;
+use model::{Category, Id, MatchResult, Quota, Student, TieBreak};
+use project_manager::ProjectManager;
+use projects::ProjectIndex;
 use rand::rngs::OsRng;
+use rand::Rng;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
 use student_block::StudentBlock;
-use web_sys::HtmlInputElement;
-use yew::format::Json;
+use wasm_bindgen::JsCast;
+use web_sys::{Element, HtmlInputElement, KeyboardEvent};
 use yew::prelude::*;
+use yew::services::keyboard::{KeyListenerHandle, KeyboardService};
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
 use yew::services::storage::{Area, StorageService};
 
-const KEY: &str = "fair_assignment.components.main";
-
 #[derive(Deserialize, Serialize, Debug)]
 pub struct State {
     categories: RefCell<Vec<Category>>,
     students: RefCell<Vec<Student>>,
     multi_matches: bool,
+    #[serde(default = "default_rounds")]
+    rounds: usize,
+    #[serde(default)]
+    max_activities: Option<usize>,
+    #[serde(default)]
+    tie_break: TieBreak,
     match_result: Option<RefCell<MatchResult>>,
+    next_id: Id,
 }
 
 impl std::default::Default for State {
@@ -55,43 +80,148 @@ impl std::default::Default for State {
             categories: RefCell::new(vec![]),
             students: RefCell::new(vec![]),
             multi_matches: false,
+            rounds: 1,
+            max_activities: None,
+            tie_break: TieBreak::Single,
             match_result: None,
+            next_id: 1,
         }
     }
 }
 
+fn default_rounds() -> usize {
+    1
+}
+
+impl State {
+    fn next_id(&mut self) -> Id {
+        let id = self.next_id;
+        self.next_id += 1;
+        id
+    }
+}
+
 #[derive(Deserialize, Serialize)]
 pub struct EphemeralState {
     editing: bool,
     show_delete_modal: bool,
+    project_error: Option<String>,
+    seed: String,
+    seed_error: Option<String>,
+    rounds_error: Option<String>,
 }
 
 pub struct Main {
     state: State,
+    projects: ProjectIndex,
     ephemeral_state: EphemeralState,
     link: ComponentLink<Self>,
     storage: StorageService,
+    history: History,
+    _key_listener: KeyListenerHandle,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
     multi_matches_ref: NodeRef,
+    project_file_ref: NodeRef,
 }
 
 pub enum Msg {
     AddCategory(Category),
+    AddCategories(Vec<Category>),
     AddStudent(Student),
-    AddPreference(Student, Category),
-    AddExclude(Student, Category),
-    MovePreference(Student, Category, Category),
-    RemovePreference((Student, Category)),
-    RemoveExclude((Student, Category)),
+    AddStudents(Vec<Student>),
+    AddPreference(Id, Id),
+    AddExclude(Id, Id),
+    MovePreference(Id, Id, Id),
+    EndMovePreference,
+    RemovePreference((Id, Id)),
+    RemoveExclude((Id, Id)),
     Editing(()),
-    EditStudent((String, String)),
-    RemoveStudent(Student),
-    EditCategory((String, String, Option<usize>)),
-    RemoveCategory(Category),
+    EditStudent((Id, String, String)),
+    RemoveStudent(Id),
+    LockStudent((Id, Option<Id>)),
+    SetGroup((Id, Option<u32>)),
+    AddSeparation((Id, Id)),
+    RemoveSeparation((Id, Id)),
+    EditCategory((Id, String, Option<usize>, usize)),
+    RemoveCategory(Id),
+    SetRounds(usize),
+    ToggleCategorySlot((Id, usize)),
+    SetQuota((Id, Option<Quota>)),
+    SetCategoryEligibility((Id, Vec<String>, Vec<String>)),
     ToggleMultiMatches,
+    SetMaxActivities(ChangeData),
+    SetStudentMaxActivities((Id, Option<usize>)),
+    SetTieBreak(ChangeData),
+    SetPriority((Id, u32)),
+    SetPriorities(Vec<(Id, u32)>),
+    SetTags((Id, Vec<String>)),
     DeleteAllData,
     MakeMatches,
+    SetSeed(String),
+    GenerateSeed,
     ChangeData,
+    MoveStudent((Id, Option<Id>, Option<Id>)),
+    ReleaseSeat((Id, Id)),
     PrintPage,
+    SaveProject,
+    OpenProject(ChangeData),
+    ProjectLoaded(FileData),
+    NewProject(String),
+    SwitchProject(u32),
+    RenameProject((u32, String)),
+    DuplicateProject(u32),
+    DeleteProject(u32),
+    Undo,
+    Redo,
+}
+
+impl Msg {
+    fn is_edit(&self) -> bool {
+        matches!(
+            self,
+            Msg::AddCategory(_)
+                | Msg::AddCategories(_)
+                | Msg::AddStudent(_)
+                | Msg::AddStudents(_)
+                | Msg::AddPreference(_, _)
+                | Msg::AddExclude(_, _)
+                | Msg::MovePreference(_, _, _)
+                | Msg::RemovePreference(_)
+                | Msg::RemoveExclude(_)
+                | Msg::EditStudent(_)
+                | Msg::RemoveStudent(_)
+                | Msg::LockStudent(_)
+                | Msg::SetGroup(_)
+                | Msg::AddSeparation(_)
+                | Msg::RemoveSeparation(_)
+                | Msg::EditCategory(_)
+                | Msg::RemoveCategory(_)
+                | Msg::SetRounds(_)
+                | Msg::ToggleCategorySlot(_)
+                | Msg::SetQuota(_)
+                | Msg::SetCategoryEligibility(_)
+                | Msg::ToggleMultiMatches
+                | Msg::SetMaxActivities(_)
+                | Msg::SetStudentMaxActivities(_)
+                | Msg::SetTieBreak(_)
+                | Msg::SetPriority(_)
+                | Msg::SetPriorities(_)
+                | Msg::SetTags(_)
+                | Msg::DeleteAllData
+                | Msg::MakeMatches
+                | Msg::ChangeData
+                | Msg::MoveStudent(_)
+                | Msg::ReleaseSeat(_)
+        )
+    }
+
+    fn edit_group(&self) -> Option<String> {
+        match self {
+            Msg::MovePreference(student_id, _, _) => Some(format!("move:{}", student_id)),
+            _ => None,
+        }
+    }
 }
 
 impl Component for Main {
@@ -99,263 +229,336 @@ impl Component for Main {
     type Properties = ();
 
     fn create(_: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let storage = StorageService::new(Area::Local).expect("storage was disabled by the user");
-        let state = {
-            if let Json(Ok(restored_model)) = storage.restore(KEY) {
-                restored_model
-            } else {
-                State::default()
-            }
-        };
+        let mut storage =
+            StorageService::new(Area::Local).expect("storage was disabled by the user");
+        let projects = ProjectIndex::restore(&mut storage);
+        let state = projects::restore_state(&storage, projects.current);
+        let key_listener = KeyboardService::register_key_down(
+            &yew::utils::window(),
+            link.batch_callback(|e: KeyboardEvent| {
+                let in_input = e
+                    .target()
+                    .and_then(|t| t.dyn_into::<Element>().ok())
+                    .map(|el| ["INPUT", "TEXTAREA", "SELECT"].contains(&el.tag_name().as_str()))
+                    .unwrap_or(false);
+                if in_input || !(e.ctrl_key() || e.meta_key()) {
+                    return vec![];
+                }
+                match e.key().to_lowercase().as_str() {
+                    "z" if e.shift_key() => {
+                        e.prevent_default();
+                        vec![Msg::Redo]
+                    }
+                    "z" => {
+                        e.prevent_default();
+                        vec![Msg::Undo]
+                    }
+                    "y" => {
+                        e.prevent_default();
+                        vec![Msg::Redo]
+                    }
+                    _ => vec![],
+                }
+            }),
+        );
 
         Self {
             state,
+            projects,
             ephemeral_state: EphemeralState {
                 editing: false,
                 show_delete_modal: false,
+                project_error: None,
+                seed: String::new(),
+                seed_error: None,
+                rounds_error: None,
             },
             link,
             storage,
+            history: History::default(),
+            _key_listener: key_listener,
+            reader: ReaderService::new(),
+            reader_task: None,
             multi_matches_ref: NodeRef::default(),
+            project_file_ref: NodeRef::default(),
         }
     }
 
     fn update(&mut self, message: Self::Message) -> ShouldRender {
+        let history_group = message.edit_group();
+        let before = if message.is_edit() {
+            serde_json::to_string(&self.state).ok()
+        } else {
+            None
+        };
+
         let response = match message {
-            Msg::AddCategory(category) => {
+            Msg::AddCategory(mut category) => {
+                category.id = self.state.next_id();
                 log::info!("Adding category: {:?}", category);
                 self.state.categories.borrow_mut().push(category);
                 true
             }
-            Msg::AddStudent(student) => {
+            Msg::AddCategories(categories) => {
+                log::info!("Adding {:?} categories", categories.len());
+                for mut category in categories {
+                    category.id = self.state.next_id();
+                    self.state.categories.borrow_mut().push(category);
+                }
+                true
+            }
+            Msg::AddStudent(mut student) => {
+                student.id = self.state.next_id();
                 log::info!("Adding student: {:?}", student);
                 self.state.students.borrow_mut().push(student);
                 true
             }
-            Msg::AddPreference(student, preference) => {
-                log::info!("Adding preference: {:?} {:?}", student, preference);
-                self.state
-                    .students
-                    .borrow_mut()
-                    .iter_mut()
-                    .find(|s| **s == student)
-                    .map(|s| s.preferences.push_back(preference));
+            Msg::AddStudents(students) => {
+                log::info!("Adding {:?} students", students.len());
+                for mut student in students {
+                    student.id = self.state.next_id();
+                    self.state.students.borrow_mut().push(student);
+                }
                 true
             }
-            Msg::AddExclude(student, exclude) => {
-                log::info!("Adding exclude: {:?} {:?}", student, exclude);
-                self.state
-                    .students
-                    .borrow_mut()
-                    .iter_mut()
-                    .find(|s| **s == student)
-                    .map(|s| s.exclude.push(exclude));
+            Msg::EndMovePreference => {
+                self.history.end_group();
+                false
+            }
+            Msg::AddPreference(student_id, category_id) => {
+                log::info!("Adding preference {:?} for {:?}", category_id, student_id);
+                self.update_student(student_id, |student| {
+                    if !student.preferences.contains(&category_id) {
+                        student.preferences.push(category_id);
+                    }
+                });
+                true
+            }
+            Msg::AddExclude(student_id, category_id) => {
+                log::info!("Adding exclude {:?} for {:?}", category_id, student_id);
+                self.update_student(student_id, |student| {
+                    if !student.exclude.contains(&category_id) {
+                        student.exclude.push(category_id);
+                    }
+                    if student.locked == Some(category_id) {
+                        student.locked = None;
+                    }
+                });
                 true
             }
-            Msg::MovePreference(student, drag_category, target_category) => {
+            Msg::MovePreference(student_id, drag_category_id, target_category_id) => {
                 log::info!(
                     "Move preference {:?} to {:?} for {:?}",
-                    &drag_category,
-                    &target_category,
-                    &student.name
+                    drag_category_id,
+                    target_category_id,
+                    student_id
                 );
-                if let Some(drag_category_index) =
-                    student.preferences.iter().position(|c| c == &drag_category)
-                {
-                    if let Some(target_category_index) = student
-                        .preferences
-                        .iter()
-                        .position(|c| c == &target_category)
+                self.update_student(student_id, |student| {
+                    let position = |id| student.preferences.iter().position(|c| *c == id);
+                    if let (Some(drag_index), Some(target_index)) =
+                        (position(drag_category_id), position(target_category_id))
                     {
-                        self.state
-                            .students
-                            .borrow_mut()
-                            .iter_mut()
-                            .find(|s| **s == student)
-                            .map(|s| {
-                                if let Some(drag_category) =
-                                    s.preferences.remove(drag_category_index)
-                                {
-                                    s.preferences.insert(target_category_index, drag_category);
-                                }
-                            });
+                        let category_id = student.preferences.remove(drag_index);
+                        student.preferences.insert(target_index, category_id);
                     }
-                }
+                });
                 true
             }
-            Msg::RemovePreference((student, preference)) => {
-                log::info!(
-                    "Remove preference {:?} for {:?}",
-                    &preference,
-                    &student.name
-                );
-
-                self.state
-                    .students
-                    .borrow_mut()
-                    .iter_mut()
-                    .find(|s| **s == student)
-                    .map(|s| {
-                        if let Some(preference_index) =
-                            student.preferences.iter().position(|c| c == &preference)
-                        {
-                            s.preferences.remove(preference_index);
-                        }
-                    });
+            Msg::RemovePreference((student_id, category_id)) => {
+                log::info!("Remove preference {:?} for {:?}", category_id, student_id);
+                self.update_student(student_id, |student| {
+                    student.preferences.retain(|c| *c != category_id);
+                });
                 true
             }
-            Msg::RemoveExclude((student, exclude)) => {
-                log::info!("Remove exclude {:?} for {:?}", &exclude, &student.name);
-
-                self.state
-                    .students
-                    .borrow_mut()
-                    .iter_mut()
-                    .find(|s| **s == student)
-                    .map(|s| {
-                        if let Some(exclude_index) =
-                            student.exclude.iter().position(|c| c == &exclude)
-                        {
-                            s.exclude.remove(exclude_index);
-                        }
-                    });
+            Msg::RemoveExclude((student_id, category_id)) => {
+                log::info!("Remove exclude {:?} for {:?}", category_id, student_id);
+                self.update_student(student_id, |student| {
+                    student.exclude.retain(|c| *c != category_id);
+                    if student.locked == Some(category_id) {
+                        student.locked = None;
+                    }
+                });
                 true
             }
-            Msg::EditStudent((pre_editing_student_name, new_student_name)) => {
+            Msg::EditStudent((student_id, new_student_name, new_class)) => {
                 log::info!(
-                    "Change student name from {:?} to {:?}",
-                    &pre_editing_student_name,
-                    &new_student_name
+                    "Change name of student {:?} to {:?} in class {:?}",
+                    student_id,
+                    &new_student_name,
+                    &new_class
                 );
-
-                if pre_editing_student_name != new_student_name
-                    && self
-                        .state
-                        .students
-                        .borrow()
-                        .iter()
-                        .find(|s| s.name == new_student_name)
-                        .is_some()
-                {
-                    self.ephemeral_state.editing = false;
-                    return true;
+                let new_student_name = new_student_name.trim();
+                if !new_student_name.is_empty() {
+                    self.update_student(student_id, |student| {
+                        student.name = new_student_name.to_string();
+                        student.class = new_class.trim().to_string();
+                    });
                 }
-
+                self.ephemeral_state.editing = false;
+                true
+            }
+            Msg::RemoveStudent(student_id) => {
+                log::info!("Remove student {:?}", student_id);
                 self.state
                     .students
                     .borrow_mut()
-                    .iter_mut()
-                    .find(|s| s.name == pre_editing_student_name)
-                    .map(|s| {
-                        s.name = new_student_name;
+                    .retain(|s| s.id != student_id);
+                for student in self.state.students.borrow_mut().iter_mut() {
+                    student.separate.retain(|s| *s != student_id);
+                }
+                true
+            }
+            Msg::LockStudent((student_id, category_id)) => {
+                log::info!("Lock student {:?} to {:?}", student_id, category_id);
+                self.update_student(student_id, |student| {
+                    student.locked = category_id;
+                });
+                true
+            }
+            Msg::SetGroup((student_id, group)) => {
+                log::info!("Set group of student {:?} to {:?}", student_id, group);
+                self.update_student(student_id, |student| {
+                    student.group = group;
+                });
+                true
+            }
+            Msg::AddSeparation((student_id, other_id)) => {
+                log::info!("Separate student {:?} from {:?}", student_id, other_id);
+                for (id, other) in [(student_id, other_id), (other_id, student_id)].iter() {
+                    self.update_student(*id, |student| {
+                        if !student.separate.contains(other) {
+                            student.separate.push(*other);
+                        }
                     });
-
-                self.ephemeral_state.editing = false;
+                }
                 true
             }
-            Msg::RemoveStudent(student) => {
-                log::info!("Remove student {:?}", &student);
-
-                let position = self
-                    .state
-                    .students
-                    .borrow_mut()
-                    .iter_mut()
-                    .position(|s| *s == student);
-
-                if let Some(index) = position {
-                    self.state.students.borrow_mut().remove(index);
+            Msg::RemoveSeparation((student_id, other_id)) => {
+                log::info!("Remove separation of {:?} from {:?}", student_id, other_id);
+                for (id, other) in [(student_id, other_id), (other_id, student_id)].iter() {
+                    self.update_student(*id, |student| {
+                        student.separate.retain(|s| s != other);
+                    });
                 }
                 true
             }
             Msg::EditCategory((
-                pre_editing_category_name,
+                category_id,
                 new_category_name,
                 new_max_placements,
+                new_min_placements,
             )) => {
                 log::info!(
-                    "Change category name from {:?} to {:?} with max_placements {:?}",
-                    &pre_editing_category_name,
+                    "Change category {:?} to name {:?} with max_placements {:?} and min_placements {:?}",
+                    category_id,
                     &new_category_name,
-                    &new_max_placements
+                    &new_max_placements,
+                    &new_min_placements
                 );
+                let new_category_name = new_category_name.trim();
+                self.ephemeral_state.editing = false;
 
-                if pre_editing_category_name != new_category_name
-                    && self
+                if new_category_name.is_empty()
+                    || self
                         .state
                         .categories
                         .borrow()
                         .iter()
-                        .find(|c| c.name == new_category_name)
-                        .is_some()
+                        .any(|c| c.id != category_id && c.name == new_category_name)
                 {
-                    self.ephemeral_state.editing = false;
                     return true;
                 }
 
-                self.state
+                if let Some(category) = self
+                    .state
                     .categories
                     .borrow_mut()
                     .iter_mut()
-                    .find(|s| s.name == pre_editing_category_name)
-                    .map(|s| {
-                        s.name = new_category_name.clone();
-                        if let Some(mp) = new_max_placements.clone() {
-                            s.max_placements = mp;
-                        }
-                    });
-
+                    .find(|c| c.id == category_id)
+                {
+                    let max_placements = new_max_placements
+                        .filter(|mp| *mp != 0)
+                        .unwrap_or(category.max_placements);
+                    if new_min_placements > max_placements {
+                        log::error!("Minimum {:?} is above the maximum", new_min_placements);
+                        return true;
+                    }
+                    category.name = new_category_name.to_string();
+                    category.max_placements = max_placements;
+                    category.min_placements = new_min_placements;
+                }
+                true
+            }
+            Msg::RemoveCategory(category_id) => {
+                log::info!("Remove category {:?}", category_id);
+                self.state
+                    .categories
+                    .borrow_mut()
+                    .retain(|c| c.id != category_id);
                 for student in self.state.students.borrow_mut().iter_mut() {
-                    let pref_position = student
-                        .preferences
-                        .iter()
-                        .position(|c| *c.name == pre_editing_category_name);
-                    if let Some(index) = pref_position {
-                        student.preferences.get_mut(index).map(|c| {
-                            c.name = new_category_name.clone();
-                            new_max_placements.clone().map(|mp| c.max_placements = mp);
-                        });
+                    student.preferences.retain(|c| *c != category_id);
+                    student.exclude.retain(|c| *c != category_id);
+                    if student.locked == Some(category_id) {
+                        student.locked = None;
                     }
-
-                    let exc_position = student
-                        .exclude
-                        .iter()
-                        .position(|c| *c.name == pre_editing_category_name);
-                    if let Some(index) = exc_position {
-                        student.exclude.get_mut(index).map(|c| {
-                            c.name = new_category_name.clone();
-                            new_max_placements.clone().map(|mp| c.max_placements = mp);
-                        });
+                }
+                true
+            }
+            Msg::SetRounds(rounds) => {
+                log::info!("Set rounds to {:?}", rounds);
+                match model::set_rounds(&mut self.state.categories.borrow_mut(), rounds) {
+                    Ok(()) => {
+                        self.state.rounds = rounds;
+                        self.ephemeral_state.rounds_error = None;
                     }
+                    Err(error) => self.ephemeral_state.rounds_error = Some(error),
                 }
-
-                self.ephemeral_state.editing = false;
                 true
             }
-            Msg::RemoveCategory(category) => {
-                log::info!("Remove category {:?}", &category);
-
-                let position = self
+            Msg::ToggleCategorySlot((category_id, slot)) => {
+                log::info!("Toggle round {:?} of category {:?}", slot, category_id);
+                let rounds = self.state.rounds;
+                if let Some(category) = self
                     .state
                     .categories
                     .borrow_mut()
                     .iter_mut()
-                    .position(|c| *c == category);
-
-                if let Some(index) = position {
-                    self.state.categories.borrow_mut().remove(index);
+                    .find(|c| c.id == category_id)
+                {
+                    self.ephemeral_state.rounds_error = category.toggle_slot(slot, rounds).err();
                 }
-
-                for student in self.state.students.borrow_mut().iter_mut() {
-                    let pref_position = student.preferences.iter().position(|c| *c == category);
-                    if let Some(index) = pref_position {
-                        student.preferences.remove(index);
-                    }
-
-                    let exc_position = student.exclude.iter().position(|c| *c == category);
-                    if let Some(index) = exc_position {
-                        student.exclude.remove(index);
-                    }
+                true
+            }
+            Msg::SetQuota((category_id, quota)) => {
+                log::info!("Set quota of category {:?} to {:?}", category_id, &quota);
+                if let Some(category) = self
+                    .state
+                    .categories
+                    .borrow_mut()
+                    .iter_mut()
+                    .find(|c| c.id == category_id)
+                {
+                    category.quota = quota;
+                }
+                true
+            }
+            Msg::SetCategoryEligibility((category_id, classes, required_tags)) => {
+                log::info!(
+                    "Category {:?} is only for classes {:?} with tags {:?}",
+                    category_id,
+                    &classes,
+                    &required_tags
+                );
+                if let Some(category) = self
+                    .state
+                    .categories
+                    .borrow_mut()
+                    .iter_mut()
+                    .find(|c| c.id == category_id)
+                {
+                    category.classes = classes;
+                    category.required_tags = required_tags;
                 }
                 true
             }
@@ -376,45 +579,251 @@ impl Component for Main {
 
                 true
             }
+            Msg::SetMaxActivities(change_data) => {
+                if let ChangeData::Value(value) = change_data {
+                    self.state.max_activities =
+                        value.trim().parse::<usize>().ok().filter(|m| *m > 0);
+                    log::info!("Max activities set to {:?}", self.state.max_activities);
+                }
+                true
+            }
+            Msg::SetStudentMaxActivities((student_id, max_activities)) => {
+                log::info!(
+                    "Set max activities of {:?} to {:?}",
+                    student_id,
+                    max_activities
+                );
+                self.update_student(student_id, |student| {
+                    student.max_activities = max_activities;
+                });
+                true
+            }
+            Msg::SetTieBreak(change_data) => {
+                if let ChangeData::Select(select) = change_data {
+                    self.state.tie_break = match select.value().as_str() {
+                        "multiple" => TieBreak::Multiple,
+                        _ => TieBreak::Single,
+                    };
+                    log::info!("Tie break set to {:?}", self.state.tie_break);
+                }
+                true
+            }
+            Msg::SetPriority((student_id, priority)) => {
+                log::info!("Set priority of {:?} to {:?}", student_id, priority);
+                self.update_student(student_id, |student| {
+                    student.priority = priority;
+                });
+                true
+            }
+            Msg::SetTags((student_id, tags)) => {
+                log::info!("Set tags of {:?} to {:?}", student_id, &tags);
+                self.update_student(student_id, |student| {
+                    student.tags = tags;
+                });
+                true
+            }
+            Msg::SetPriorities(priorities) => {
+                log::info!("Set priorities of {:?} students", priorities.len());
+                for (student_id, priority) in priorities {
+                    self.update_student(student_id, |student| {
+                        student.priority = priority;
+                    });
+                }
+                true
+            }
             Msg::DeleteAllData => {
                 self.state = State::default();
                 true
             }
             Msg::MakeMatches => {
                 log::info!("Making matches...");
-                let mut rng = OsRng;
-                log::info!("Multi matches: {:?}", self.state.multi_matches);
-                let match_result = if self.state.multi_matches {
-                    log::info!("Make multi matches");
-                    match_students_to_multiple_categories(
-                        self.state.students.borrow().clone(),
-                        &*self.state.categories.borrow(),
-                        &mut rng,
-                    )
+                let seed = self.ephemeral_state.seed.trim();
+                let seed = if seed.is_empty() {
+                    random_seed()
+                } else if let Ok(seed) = seed.parse::<u64>() {
+                    seed
                 } else {
-                    log::info!("Make single matches");
-                    match_students(
-                        self.state.students.borrow().clone(),
-                        &*self.state.categories.borrow(),
-                        &mut rng,
-                    )
+                    self.ephemeral_state.seed_error =
+                        Some("Het startgetal mag alleen uit cijfers bestaan.".into());
+                    return true;
                 };
+                self.ephemeral_state.seed_error = None;
+                log::info!("Multi matches: {:?}", self.state.multi_matches);
+                log::info!("Seed: {:?}", seed);
+                log::info!("Tie break: {:?}", self.state.tie_break);
+                let match_result = matching::make_matches(
+                    &self.state.students.borrow(),
+                    &self.state.categories.borrow(),
+                    &MatchOptions {
+                        tie_break: self.state.tie_break,
+                        multi_matches: self.state.multi_matches,
+                        rounds: self.state.rounds,
+                        max_activities: self.state.max_activities,
+                        seed,
+                    },
+                );
                 self.state.match_result = Some(RefCell::new(match_result));
                 log::info!("Matches made: {:?}", &self.state.match_result);
                 true
             }
+            Msg::SetSeed(seed) => {
+                self.ephemeral_state.seed = seed;
+                self.ephemeral_state.seed_error = None;
+                false
+            }
+            Msg::GenerateSeed => {
+                self.ephemeral_state.seed = random_seed().to_string();
+                self.ephemeral_state.seed_error = None;
+                true
+            }
             Msg::ChangeData => {
                 self.state.match_result = None;
                 true
             }
+            Msg::MoveStudent((student_id, from, to)) => {
+                log::info!("Move student {:?} from {:?} to {:?}", student_id, from, to);
+                if let Some(match_result) = &self.state.match_result {
+                    if let Err(error) = matching::move_student(
+                        &mut match_result.borrow_mut(),
+                        &self.state.students.borrow(),
+                        &self.state.categories.borrow(),
+                        student_id,
+                        None,
+                        from,
+                        to,
+                    ) {
+                        log::error!("Could not move student: {}", error);
+                    }
+                }
+                true
+            }
+            Msg::ReleaseSeat((student_id, category_id)) => {
+                log::info!("Release seat of {:?} in {:?}", student_id, category_id);
+                if let Some(match_result) = &self.state.match_result {
+                    if let Err(error) = matching::release_seat(
+                        &mut match_result.borrow_mut(),
+                        &self.state.students.borrow(),
+                        &self.state.categories.borrow(),
+                        student_id,
+                        category_id,
+                    ) {
+                        log::error!("Could not release seat: {}", error);
+                    }
+                }
+                true
+            }
             Msg::PrintPage => {
                 external::print_page();
                 false
             }
+            Msg::SaveProject => {
+                match project_file::to_json(&self.state) {
+                    Ok(content) => {
+                        external::download_file(
+                            "eerlijke-indeling-project.json",
+                            project_file::JSON_MIME_TYPE,
+                            &content,
+                        );
+                        self.ephemeral_state.project_error = None;
+                    }
+                    Err(error) => self.ephemeral_state.project_error = Some(error),
+                }
+                true
+            }
+            Msg::OpenProject(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::ProjectLoaded);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.reader_task = Some(task),
+                        Err(e) => {
+                            log::error!("Could not read project file: {:?}", e);
+                            self.ephemeral_state.project_error =
+                                Some("Het bestand kon niet worden gelezen.".into());
+                        }
+                    }
+                }
+                true
+            }
+            Msg::OpenProject(_) => false,
+            Msg::ProjectLoaded(file_data) => {
+                log::info!("Opening project {:?}", &file_data.name);
+                self.reader_task = None;
+                if let Some(input) = self.project_file_ref.cast::<HtmlInputElement>() {
+                    input.set_value("");
+                }
+                match project_file::from_json(&file_data.content) {
+                    Ok(state) => {
+                        let name = file_data.name.trim_end_matches(".json");
+                        self.projects.current = self.projects.create(name);
+                        self.state = state;
+                        self.history.clear();
+                        self.ephemeral_state.project_error = None;
+                    }
+                    Err(error) => {
+                        log::error!("Invalid project file: {}", &error);
+                        self.ephemeral_state.project_error = Some(error);
+                    }
+                }
+                true
+            }
+            Msg::NewProject(name) => {
+                log::info!("New project {:?}", &name);
+                self.projects.current = self.projects.create(&name);
+                self.state = State::default();
+                self.history.clear();
+                self.ephemeral_state.editing = false;
+                true
+            }
+            Msg::SwitchProject(id) => {
+                log::info!("Switch to project {:?}", id);
+                self.projects.current = id;
+                self.state = projects::restore_state(&self.storage, id);
+                self.history.clear();
+                self.ephemeral_state.editing = false;
+                true
+            }
+            Msg::RenameProject((id, name)) => {
+                log::info!("Rename project {:?} to {:?}", id, &name);
+                self.projects.rename(id, &name);
+                true
+            }
+            Msg::DuplicateProject(id) => {
+                log::info!("Duplicate project {:?}", id);
+                self.projects.duplicate(&mut self.storage, id);
+                true
+            }
+            Msg::DeleteProject(id) => {
+                log::info!("Delete project {:?}", id);
+                let was_current = id == self.projects.current;
+                self.projects.delete(&mut self.storage, id);
+                if was_current {
+                    self.state = projects::restore_state(&self.storage, self.projects.current);
+                    self.history.clear();
+                }
+                true
+            }
+            Msg::Undo => {
+                let snapshot = serde_json::to_string(&self.state)
+                    .ok()
+                    .and_then(|current| self.history.undo(current));
+                self.restore_snapshot(snapshot)
+            }
+            Msg::Redo => {
+                let snapshot = serde_json::to_string(&self.state)
+                    .ok()
+                    .and_then(|current| self.history.redo(current));
+                self.restore_snapshot(snapshot)
+            }
         };
 
-        let json = Json(&self.state);
-        self.storage.store(KEY, json);
+        if let Some(before) = before {
+            if serde_json::to_string(&self.state).ok().as_ref() != Some(&before) {
+                self.history.record(before, history_group);
+            }
+        }
+
+        projects::store_state(&mut self.storage, self.projects.current, &self.state);
+        self.projects.store(&mut self.storage);
 
         response
     }
@@ -425,6 +834,9 @@ impl Component for Main {
 
     fn view(&self) -> Html {
         let handle_on_add_category = self.link.callback(Msg::AddCategory);
+        let handle_on_add_categories = self.link.callback(Msg::AddCategories);
+        let handle_on_add_students = self.link.callback(Msg::AddStudents);
+        let handle_on_end_move_preference = self.link.callback(|_| Msg::EndMovePreference);
         let handle_on_add_student = self
             .link
             .callback(|student: Student| Msg::AddStudent(student));
@@ -444,8 +856,16 @@ impl Component for Main {
         let handle_on_edit_student = self.link.callback(Msg::EditStudent);
         let handle_on_editing = self.link.callback(Msg::Editing);
         let handle_on_remove_student = self.link.callback(Msg::RemoveStudent);
+        let handle_on_lock_student = self.link.callback(Msg::LockStudent);
+        let handle_on_set_group = self.link.callback(Msg::SetGroup);
+        let handle_on_add_separation = self.link.callback(Msg::AddSeparation);
+        let handle_on_remove_separation = self.link.callback(Msg::RemoveSeparation);
         let handle_on_remove_category = self.link.callback(Msg::RemoveCategory);
         let handle_on_edit_category = self.link.callback(Msg::EditCategory);
+        let handle_on_set_rounds = self.link.callback(Msg::SetRounds);
+        let handle_on_toggle_category_slot = self.link.callback(Msg::ToggleCategorySlot);
+        let handle_on_set_quota = self.link.callback(Msg::SetQuota);
+        let handle_on_set_category_eligibility = self.link.callback(Msg::SetCategoryEligibility);
 
         let handle_toggle_multitmatches =
             self.link.callback(|_: MouseEvent| Msg::ToggleMultiMatches);
@@ -454,14 +874,52 @@ impl Component for Main {
             e.prevent_default();
             Msg::MakeMatches
         });
+        let handle_set_seed = self.link.callback(|e: InputData| Msg::SetSeed(e.value));
+        let handle_generate_seed = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::GenerateSeed
+        });
         let handle_change_data = self.link.callback(|e: MouseEvent| {
             e.prevent_default();
             Msg::ChangeData
         });
+        let handle_move_student = self.link.callback(Msg::MoveStudent);
+        let handle_release_seat = self.link.callback(Msg::ReleaseSeat);
+        let handle_set_max_activities = self.link.callback(Msg::SetMaxActivities);
+        let handle_on_set_max_activities = self.link.callback(Msg::SetStudentMaxActivities);
+        let handle_set_tie_break = self.link.callback(Msg::SetTieBreak);
+        let multiple_tie_break = self.state.tie_break == TieBreak::Multiple;
+        let handle_on_set_priority = self.link.callback(Msg::SetPriority);
+        let handle_on_set_priorities = self.link.callback(Msg::SetPriorities);
+        let handle_on_set_tags = self.link.callback(Msg::SetTags);
+        let max_activities = self
+            .state
+            .max_activities
+            .map(|m| m.to_string())
+            .unwrap_or_default();
+        let schedule = self.state.rounds > 1;
         let handle_print_page = self.link.callback(|e: MouseEvent| {
             e.prevent_default();
             Msg::PrintPage
         });
+        let handle_save_project = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::SaveProject
+        });
+        let handle_open_project = self.link.callback(Msg::OpenProject);
+        let handle_new_project = self.link.callback(Msg::NewProject);
+        let handle_switch_project = self.link.callback(Msg::SwitchProject);
+        let handle_rename_project = self.link.callback(Msg::RenameProject);
+        let handle_duplicate_project = self.link.callback(Msg::DuplicateProject);
+        let handle_delete_project = self.link.callback(Msg::DeleteProject);
+        let handle_undo = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Undo
+        });
+        let handle_redo = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Redo
+        });
 
         html! {
             <>
@@ -469,24 +927,49 @@ impl Component for Main {
                 <div class="row d-print-none mt-3">
                     <div class="col shadow p-3 mb-5 bg-white rounded">
                         <h1>{"Eerlijke Indeling"}</h1>
+                        <p class="text-muted">{ format!("Project: {}", self.projects.current_name()) }</p>
                         <p>{ "Eerlijke Indeling is een veilige applicatie om leerlingen in te delen bij activiteiten of workshops. Om een rechtvaardige verdeling te maken, wordt gebruik gemaakt van het algoritme dat in Amsterdam wordt ingezet bij toewijzing van leerlingen aan scholen." }</p>
+                        <div class="btn-group float-left" role="group">
+                            <button name="undo" class="btn btn-outline-secondary btn-sm" title="Ctrl+Z" disabled=!self.history.can_undo() onclick=handle_undo>{ "Ongedaan maken" }</button>
+                            <button name="redo" class="btn btn-outline-secondary btn-sm" title="Ctrl+Shift+Z" disabled=!self.history.can_redo() onclick=handle_redo>{ "Opnieuw" }</button>
+                        </div>
                         <button name="more_info" class="btn btn-info btn-sm float-right" data-toggle="modal" data-target="#more_info_modal">{ "Over deze applicatie" }</button>
+                        <button name="projects" class="btn btn-secondary btn-sm float-right mr-2" data-toggle="modal" data-target="#projects_modal">{ "Projecten" }</button>
+                        <button name="save_project" class="btn btn-secondary btn-sm float-right mr-2" onclick=handle_save_project>{ "Project opslaan" }</button>
+                        <label class="btn btn-secondary btn-sm float-right mr-2 mb-0" title="Het geopende bestand wordt als nieuw project toegevoegd.">
+                            { "Project openen" }
+                            <input type="file" accept=".json,application/json" hidden=true onchange=handle_open_project ref=self.project_file_ref.clone() />
+                        </label>
+                        {
+                            if let Some(error) = &self.ephemeral_state.project_error {
+                                html! { <div class="invalid-feedback d-block">{ error }</div> }
+                            } else {
+                                html! {}
+                            }
+                        }
                     </div>
                 </div>
                 {
                     if let Some(match_result) = &self.state.match_result {
+                        let waitlists = matching::waitlists(
+                            &match_result.borrow(),
+                            &self.state.students.borrow(),
+                            &self.state.categories.borrow(),
+                        );
                         html! {
-                            <MatchResultBlock match_result=match_result.clone() categories=self.state.categories.clone() />
+                            <MatchResultBlock match_result=match_result.clone() categories=self.state.categories.clone() students=self.state.students.clone() on_move_student=handle_move_student on_release_seat=handle_release_seat waitlists=waitlists />
                         }
                     } else {
                         html! {
                             <>
                                 <div class="row">
-                                    <CategoryBlock categories=self.state.categories.clone() editing=self.ephemeral_state.editing on_add_category=handle_on_add_category on_edit_category=handle_on_edit_category on_editing=handle_on_editing.clone() on_remove_category=handle_on_remove_category />
+                                    <CategoryBlock categories=self.state.categories.clone() editing=self.ephemeral_state.editing on_add_category=handle_on_add_category on_add_categories=handle_on_add_categories on_edit_category=handle_on_edit_category on_editing=handle_on_editing.clone() on_remove_category=handle_on_remove_category rounds=self.state.rounds rounds_error=self.ephemeral_state.rounds_error.clone() on_set_rounds=handle_on_set_rounds on_toggle_category_slot=handle_on_toggle_category_slot on_set_quota=handle_on_set_quota on_set_category_eligibility=handle_on_set_category_eligibility />
                                 </div>
                                 <div class="row">
-                                    <StudentBlock students=self.state.students.clone() categories=self.state.categories.clone() editing=self.ephemeral_state.editing on_editing=handle_on_editing.clone() on_add_student=handle_on_add_student on_add_preference=handle_on_add_preference on_add_exclude=handle_on_add_exclude on_move_preference=handle_on_move_preference.clone() on_remove_preference=handle_on_remove_preference.clone() on_remove_exclude=handle_on_remove_exclude.clone() on_edit_student=handle_on_edit_student.clone() on_remove_student=handle_on_remove_student.clone() />
+                                    <StudentBlock students=self.state.students.clone() categories=self.state.categories.clone() editing=self.ephemeral_state.editing on_editing=handle_on_editing.clone() on_add_student=handle_on_add_student on_add_students=handle_on_add_students on_add_preference=handle_on_add_preference on_add_exclude=handle_on_add_exclude on_move_preference=handle_on_move_preference.clone() on_end_move_preference=handle_on_end_move_preference on_remove_preference=handle_on_remove_preference.clone() on_remove_exclude=handle_on_remove_exclude.clone() on_edit_student=handle_on_edit_student.clone() on_remove_student=handle_on_remove_student.clone() on_lock_student=handle_on_lock_student on_set_group=handle_on_set_group on_add_separation=handle_on_add_separation on_remove_separation=handle_on_remove_separation multi_matches=self.state.multi_matches && !schedule max_activities=self.state.max_activities on_set_max_activities=handle_on_set_max_activities on_set_priority=handle_on_set_priority on_set_tags=handle_on_set_tags />
                                 </div>
+                                <DemandBlock categories=self.state.categories.clone() students=self.state.students.clone() multi_matches=self.state.multi_matches rounds=self.state.rounds />
+                                <CarryOverBlock projects=self.projects.projects.clone() current=self.projects.current students=self.state.students.clone() on_set_priorities=handle_on_set_priorities editing=self.ephemeral_state.editing />
                             </>
                         }
                     }
@@ -499,14 +982,15 @@ impl Component for Main {
                                 html! {
                                     <>
                                     <p>{ "Klik op 'Indeling' maken om de leerlingen eerlijk te verdelen over de ingevoerde activiteiten." }</p>
-                                    <p>{ "Standaard wordt iedere leerling toegewezen aan één van de activiteiten. Kunnen leerlingen aan meer dan één activiteit meedoen, bijvoorbeeld tijdens een sportdag of wanneer de activiteiten op verschillende dagen plaatsvinden? Selecteer dan het vinkje 'Leerlingen kunnen aan meerdere activiteiten meedoen'." }</p>
+                                    <p>{ "Standaard wordt iedere leerling toegewezen aan één van de activiteiten. Kunnen leerlingen aan meer dan één activiteit meedoen, bijvoorbeeld tijdens een sportdag of wanneer de activiteiten op verschillende dagen plaatsvinden? Selecteer dan het vinkje 'Leerlingen kunnen aan meerdere activiteiten meedoen'. Je kunt dan ook een maximum aantal activiteiten per leerling instellen. Iedereen krijgt eerst één activiteit, daarna een tweede, enzovoort." }</p>
+                                    <p>{ "Bij gelijke voorkeuren beslist een loting. Wil je een indeling later precies zo opnieuw kunnen maken, vul dan een startgetal in of laat er een genereren. Het gebruikte startgetal staat altijd bij de indeling." }</p>
                                     </>
                                 }
                             } else {
                                 html! {
                                     <>
-                                    <p>{ "Je kunt nu de gemaakte indeling printen of handmatig kopiëren naar Word of Excel. Als je nog iets wilt aanpassen, klik je op 'Gegevens aanpassen'." }</p>
-                                    <p>{ "Let op: klik alleen op de knop 'Alles verwijderen' als je de gemaakte indeling hebt gekopieerd. Je invoer wordt gewist en alle velden zijn hierna leeg. Zo kun je weer een nieuwe indeling maken." }</p>
+                                    <p>{ "Je kunt nu de gemaakte indeling printen of downloaden als Excel- of CSV-bestand. Als je nog iets wilt aanpassen, klik je op 'Gegevens aanpassen'." }</p>
+                                    <p>{ "Let op: klik alleen op de knop 'Alles verwijderen' als je de gemaakte indeling hebt bewaard. Je invoer wordt gewist en alle velden zijn hierna leeg. Zo kun je weer een nieuwe indeling maken." }</p>
                                     </>
                                 }
                             }
@@ -531,9 +1015,32 @@ impl Component for Main {
                                             html! {
                                                 <>
                                                     <div class="form-group form-check mr-sm-3">
-                                                        <input type="checkbox" class="form-check-input" id="multi_matches" ref=self.multi_matches_ref.clone() onclick=handle_toggle_multitmatches checked=self.state.multi_matches />
+                                                        <input type="checkbox" class="form-check-input" id="multi_matches" ref=self.multi_matches_ref.clone() onclick=handle_toggle_multitmatches checked=self.state.multi_matches disabled=schedule />
                                                         <label class="form-check-label" for="multi_matches" data-toggle="tooltip" title="Standaard wordt iedere leerling in slechts één activiteit ingedeeld. Door er voor te kiezen leerlingen aan meerdere activiteiten mee te laten doen, worden leerlingen ingedeeld aan alle activiteiten waar zij aan mee willen doen, zolang er plekken zijn binnen deze activiteiten. Hierbij wordt rekening gehouden met de voorkeuren van de leerling.">{ "Leerlingen kunnen aan meerdere activiteiten meedoen" }</label>
                                                     </div>
+                                                    {
+                                                        if self.state.multi_matches && !schedule {
+                                                            html! {
+                                                                <div class="form-group mr-sm-3">
+                                                                    <input type="number" class="form-control form-control-sm" name="max_activities" min="1" placeholder="Max. activiteiten" value=max_activities onchange=handle_set_max_activities title="Het maximum aantal activiteiten per leerling. Laat leeg voor geen maximum. Per leerling kun je hiervan afwijken in stap 2." />
+                                                                </div>
+                                                            }
+                                                        } else {
+                                                            html! {}
+                                                        }
+                                                    }
+                                                    <div class="form-group mr-sm-3">
+                                                        <select class="form-control form-control-sm" name="tie_break" onchange=handle_set_tie_break title="Bij één loting krijgt iedere leerling één lotnummer dat voor alle activiteiten geldt. Bij een aparte loting per activiteit wordt voor iedere activiteit opnieuw geloot, zodat een slecht lotnummer niet voor alle activiteiten geldt.">
+                                                            <option value="single" selected=!multiple_tie_break>{ "Eén loting voor alle activiteiten" }</option>
+                                                            <option value="multiple" selected=multiple_tie_break>{ "Een aparte loting per activiteit" }</option>
+                                                        </select>
+                                                    </div>
+                                                    <div class="input-group input-group-sm mr-sm-3">
+                                                        <input type="text" class="form-control" name="seed" placeholder="Startgetal (optioneel)" inputmode="numeric" value=&self.ephemeral_state.seed oninput=handle_set_seed title="Met hetzelfde startgetal en dezelfde gegevens wordt precies dezelfde indeling gemaakt. Laat je het veld leeg, dan wordt een willekeurig startgetal gekozen." />
+                                                        <div class="input-group-append">
+                                                            <button name="generate_seed" class="btn btn-outline-secondary" onclick=handle_generate_seed>{ "Genereer" }</button>
+                                                        </div>
+                                                    </div>
                                                     <button name="make_matches" class="btn btn-success" onclick=handle_make_matches>{ "Indeling maken" }</button>
                                                 </>
                                             }
@@ -544,6 +1051,13 @@ impl Component for Main {
                                         }
                                     }
                                 </form>
+                                {
+                                    if let Some(error) = &self.ephemeral_state.seed_error {
+                                        html! { <div class="invalid-feedback d-block text-right">{ error }</div> }
+                                    } else {
+                                        html! {}
+                                    }
+                                }
                             </div>
                         </div>
                     </div>
@@ -553,7 +1067,8 @@ impl Component for Main {
             <Modal id="more_info_modal" title="Over deze applicatie" btn_label="" btn_type="" handle_modal_action=None >
                 <p>{ "Alle ingevoerde gegevens voor de applicatie Eerlijke Indeling worden enkel in de browser van je eigen computer opgeslagen. Er wordt dus géén data naar een server of externe partijen verstuurd. De knop 'Alles verwijderen' zorgt ook daadwerkelijk dat alle gegevens worden verwijderd. De namen van de leerlingen zijn daarmee veilig en hun privacy blijft gewaarborgd. Let op dat de uiteindelijke Eerlijke Indeling daarmee ook komt te verdwijnen, noteer deze dus tijdig." }</p>
                 <p>{ "Zonder het gebruik van de knop 'Alles Verwijderen' blijven je gegevens bewaard, ook na het sluiten van je browser. Dat betekent dat je op een later moment je Eerlijke Indeling af kunt maken, aan kunt passen of opnieuw kunt kopiëren." }</p>
-                <p>{ "De achterliggende techniek waar deze website gebruik van maakt, is het zogenaamde Deferred Acceptance, Single-Tie-Break algoritme. Meer informatie over dit algoritme en de gebruikte manier van indelen kun je vinden in het document " }<a href="https://staff.fnwi.uva.nl/b.bredeweg/pdf/BSc/20152016/Klijnsma.pdf" target="_blank">{ "Matching algorithms for the secondary school admission problem in Amsterdam" }</a></p>
+                <p>{ "Met de knop 'Project opslaan' download je alle ingevoerde gegevens als bestand. Dit bestand kun je bewaren als reservekopie of op een andere computer weer inladen met 'Project openen'." }</p>
+                <p>{ "De achterliggende techniek waar deze website gebruik van maakt, is het zogenaamde Deferred Acceptance algoritme. Standaard wordt daarbij één loting voor alle activiteiten gehouden (Single-Tie-Break), bij stap 3 kun je ook kiezen voor een aparte loting per activiteit (Multiple-Tie-Break). Meer informatie over dit algoritme en de gebruikte manier van indelen kun je vinden in het document " }<a href="https://staff.fnwi.uva.nl/b.bredeweg/pdf/BSc/20152016/Klijnsma.pdf" target="_blank">{ "Matching algorithms for the secondary school admission problem in Amsterdam" }</a></p>
                 <h5>{ "Contact" }</h5>
                 <p>{ "Mocht je vragen, ideeën of opmerkingen hebben, neem dan contact op via onderstaand formulier." }</p>
                 <form name="contact" method="POST">
@@ -582,10 +1097,48 @@ impl Component for Main {
                 <p>{ "Deze app is gedistribueerd in de hoop dat het nuttig zal zijn maar ZONDER ENIGE GARANTIE; zelfs zonder de impliciete garanties die GEBRUIKELIJK ZIJN IN DE HANDEL of voor BRUIKBAARHEID VOOR EEN SPECIFIEK DOEL." }</p>
                 <p>{ "De broncode voor deze app is beschikbaar op " }<a href="https://github.com/deliriouspenguin/eerlijke-indeling" target="_blank">{ "https://github.com/deliriouspenguin/eerlijke-indeling" }</a></p>
             </Modal>
+            <Modal id="projects_modal" title="Projecten" btn_label="" btn_type="" handle_modal_action=None >
+                <p>{ "Maak voor iedere groep of gelegenheid een apart project aan. Alle projecten worden in je browser bewaard." }</p>
+                <ProjectManager projects=self.projects.projects.clone() current=self.projects.current on_new_project=handle_new_project on_switch_project=handle_switch_project on_rename_project=handle_rename_project on_duplicate_project=handle_duplicate_project on_delete_project=handle_delete_project />
+            </Modal>
             <Modal id="confirm_delete_all_modal" title="Alles verwijderen" btn_label="Alles verwijderen" btn_type="danger" handle_modal_action=Some(handle_delete_all_data)>
-                { "Weet je zeker dat je ingevoerde gegevens wilt verwijderen? Dit is niet ongedaan te maken." }
+                { "Weet je zeker dat je ingevoerde gegevens van dit project wilt verwijderen? Dit kun je alleen direct daarna nog ongedaan maken." }
             </Modal>
         </>
         }
     }
 }
+
+impl Main {
+    fn update_student<F: FnOnce(&mut Student)>(&self, id: Id, update: F) {
+        if let Some(student) = self
+            .state
+            .students
+            .borrow_mut()
+            .iter_mut()
+            .find(|s| s.id == id)
+        {
+            update(student);
+        }
+    }
+
+    fn restore_snapshot(&mut self, snapshot: Option<String>) -> ShouldRender {
+        match snapshot.map(|snapshot| serde_json::from_str::<State>(&snapshot)) {
+            Some(Ok(state)) => {
+                self.state = state;
+                self.ephemeral_state.editing = false;
+                true
+            }
+            Some(Err(e)) => {
+                log::error!("Could not restore snapshot: {:?}", e);
+                false
+            }
+            None => false,
+        }
+    }
+}
+
+// Generated seeds are kept short, so they are easy to write down and type in.
+fn random_seed() -> u64 {
+    OsRng.gen_range(0, 1_000_000_000)
+}
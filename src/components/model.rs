@@ -0,0 +1,485 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::State;
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+pub type Id = u32;
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Category {
+    pub id: Id,
+    pub name: String,
+    pub max_placements: usize,
+    // The activity is cancelled when fewer students are placed in it.
+    #[serde(default)]
+    pub min_placements: usize,
+    #[serde(default, alias = "class_quota")]
+    pub quota: Option<Quota>,
+    // Only students of these classes may take part, all students when empty.
+    #[serde(default)]
+    pub classes: Vec<String>,
+    // Students need all these tags to take part.
+    #[serde(default)]
+    pub required_tags: Vec<String>,
+    // The rounds (time slots) in which the activity runs, all rounds when
+    // empty.
+    #[serde(default)]
+    pub slots: Vec<usize>,
+}
+
+impl Category {
+    // The id is assigned when the category is added to the project.
+    pub fn new(name: &str, max_placements: usize) -> Self {
+        Category {
+            id: 0,
+            name: name.to_string(),
+            max_placements,
+            min_placements: 0,
+            quota: None,
+            classes: vec![],
+            required_tags: vec![],
+            slots: vec![],
+        }
+    }
+
+    pub fn admits(&self, student: &Student) -> bool {
+        (self.classes.is_empty() || self.classes.contains(&student.class))
+            && self
+                .required_tags
+                .iter()
+                .all(|tag| student.tags.contains(tag))
+    }
+
+    pub fn runs_in(&self, slot: usize) -> bool {
+        self.slots.is_empty() || self.slots.contains(&slot)
+    }
+
+    // As empty slots mean every round, the last round of an activity can not
+    // be switched off. Remove the activity instead.
+    pub fn toggle_slot(&mut self, slot: usize, rounds: usize) -> Result<(), String> {
+        let mut slots: Vec<usize> = (0..rounds).filter(|s| self.runs_in(*s)).collect();
+        if slots.contains(&slot) {
+            slots.retain(|s| *s != slot);
+        } else {
+            slots.push(slot);
+            slots.sort_unstable();
+        }
+        if slots.is_empty() {
+            return Err(format!(
+                "{} moet in minstens één ronde plaatsvinden.",
+                self.name
+            ));
+        }
+        self.slots = if slots.len() == rounds { vec![] } else { slots };
+        Ok(())
+    }
+
+    // The tag the quota of the activity applies to, if any.
+    pub fn quota_tag(&self) -> Option<&str> {
+        match self.quota.as_ref().map(|quota| &quota.attribute) {
+            Some(QuotaAttribute::Tag(tag)) => Some(tag),
+            _ => None,
+        }
+    }
+
+    // The number of places one class, or the students with the tag, may take,
+    // at least one.
+    pub fn quota_limit(&self) -> Option<usize> {
+        self.quota
+            .as_ref()
+            .map(|quota| (self.max_placements * quota.percentage / 100).max(1))
+    }
+
+    // With rounds, the places are available once per round in which the
+    // activity runs.
+    pub fn total_places(&self, rounds: usize) -> usize {
+        if rounds > 1 {
+            self.max_placements * (0..rounds).filter(|r| self.runs_in(*r)).count()
+        } else {
+            self.max_placements
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Quota {
+    #[serde(default)]
+    pub attribute: QuotaAttribute,
+    // The share of the places one class, or the students with the tag, may
+    // take, in percent.
+    pub percentage: usize,
+    // A soft quota may be exceeded to fill places that would otherwise stay
+    // empty.
+    pub hard: bool,
+}
+
+impl Quota {
+    // The students the quota counts the student with, if any. Students without
+    // a class never count towards a class quota.
+    pub fn group<'a>(&'a self, student: &'a Student) -> Option<&'a str> {
+        match &self.attribute {
+            QuotaAttribute::Class => Some(student.class.as_str()).filter(|c| !c.is_empty()),
+            QuotaAttribute::Tag(tag) => Some(tag.as_str()).filter(|_| student.tags.contains(tag)),
+        }
+    }
+
+    pub fn describe_group(&self, group: &str) -> String {
+        match self.attribute {
+            QuotaAttribute::Class => format!("uit klas {}", group),
+            QuotaAttribute::Tag(_) => format!("met kenmerk {}", group),
+        }
+    }
+}
+
+// A quota either limits every class, or only the students with a tag.
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug, Default)]
+pub enum QuotaAttribute {
+    #[default]
+    Class,
+    Tag(String),
+}
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct Student {
+    pub id: Id,
+    pub name: String,
+    // The class of the student, e.g. "2A". Empty when unknown.
+    #[serde(default)]
+    pub class: String,
+    // Attributes such as "zwemdiploma" that activities can require.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    pub preferences: Vec<Id>,
+    pub exclude: Vec<Id>,
+    // A locked student is placed in this category before matching.
+    #[serde(default)]
+    pub locked: Option<Id>,
+    // Students with the same group number are matched together.
+    #[serde(default)]
+    pub group: Option<u32>,
+    // Students that may never be placed in the same activity as this one.
+    #[serde(default)]
+    pub separate: Vec<Id>,
+    // Overrides the maximum number of activities of the project.
+    #[serde(default)]
+    pub max_activities: Option<usize>,
+    // Students with a higher priority tier get a better lottery position
+    // than all students with a lower one.
+    #[serde(default)]
+    pub priority: u32,
+}
+
+impl Student {
+    // The id is assigned when the student is added to the project.
+    pub fn new(name: &str, preferences: Vec<Id>, exclude: Vec<Id>) -> Self {
+        Student {
+            id: 0,
+            name: name.to_string(),
+            class: String::new(),
+            tags: vec![],
+            preferences,
+            exclude,
+            locked: None,
+            group: None,
+            separate: vec![],
+            max_activities: None,
+            priority: 0,
+        }
+    }
+}
+
+// Single: one lottery decides between the students for all activities (DA-STB).
+// Multiple: every activity holds a lottery of its own (DA-MTB).
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum TieBreak {
+    #[default]
+    Single,
+    Multiple,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct MatchResult {
+    pub placed: BTreeMap<Id, Vec<Id>>,
+    pub not_placable: Vec<Id>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub tie_break: TieBreak,
+    // Student ids in lottery order, the first student has the best position.
+    #[serde(default)]
+    pub lottery: Vec<Id>,
+    // The lottery of every activity with multiple tie-breaks.
+    #[serde(default)]
+    pub category_lotteries: BTreeMap<Id, Vec<Id>>,
+    // The priority tier every student was drawn with in the lottery, the
+    // tier of their group for group members. Only tiers above zero.
+    #[serde(default)]
+    pub priorities: BTreeMap<Id, u32>,
+    // The placements per round when matching with time slots. The placements
+    // of all rounds together are in `placed`.
+    #[serde(default)]
+    pub slots: Vec<SlotResult>,
+    #[serde(default)]
+    pub cancelled: Vec<CancelledCategory>,
+    // Students that gave up their seat after the matching.
+    #[serde(default)]
+    pub released: Vec<Id>,
+}
+
+impl MatchResult {
+    // The lottery that decided between the students for the given activity.
+    pub fn lottery_for(&self, category_id: Id) -> &[Id] {
+        self.category_lotteries
+            .get(&category_id)
+            .unwrap_or(&self.lottery)
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct CancelledCategory {
+    pub category_id: Id,
+    // The number of participants when the activity was cancelled.
+    pub participants: usize,
+}
+
+#[derive(Deserialize, Serialize, Clone, Debug, Default)]
+pub struct SlotResult {
+    pub placed: BTreeMap<Id, Vec<Id>>,
+    pub not_placable: Vec<Id>,
+}
+
+pub fn category_by_id(categories: &[Category], id: Id) -> Option<&Category> {
+    categories.iter().find(|c| c.id == id)
+}
+
+pub fn student_by_id(students: &[Student], id: Id) -> Option<&Student> {
+    students.iter().find(|s| s.id == id)
+}
+
+// Splits a comma separated list as typed by the user, e.g. "3A, 3B".
+pub fn parse_list(value: &str) -> Vec<String> {
+    let mut items: Vec<String> = vec![];
+    for item in value.split(',').map(|i| i.trim()).filter(|i| !i.is_empty()) {
+        if !items.iter().any(|i| i == item) {
+            items.push(item.to_string());
+        }
+    }
+    items
+}
+
+pub fn classes(students: &[Student]) -> Vec<String> {
+    let classes: BTreeSet<&String> = students
+        .iter()
+        .map(|s| &s.class)
+        .filter(|c| !c.is_empty())
+        .collect();
+    classes.into_iter().cloned().collect()
+}
+
+// Before students and categories had ids, the state consisted of the types
+// of the matchmaker crate, which are matched by name. Only the fields needed
+// for the conversion are read.
+#[derive(Deserialize)]
+pub struct LegacyState {
+    categories: Vec<LegacyCategory>,
+    students: Vec<LegacyStudent>,
+    multi_matches: bool,
+    match_result: Option<LegacyMatchResult>,
+}
+
+#[derive(Deserialize)]
+struct LegacyCategory {
+    name: String,
+    max_placements: usize,
+}
+
+#[derive(Deserialize)]
+struct LegacyStudent {
+    name: String,
+    preferences: Vec<LegacyCategory>,
+    exclude: Vec<LegacyCategory>,
+}
+
+#[derive(Deserialize)]
+struct LegacyMatchResult {
+    placed: HashMap<String, Vec<LegacyStudent>>,
+    not_placable: Vec<LegacyStudent>,
+}
+
+impl From<LegacyState> for State {
+    fn from(legacy: LegacyState) -> Self {
+        let mut next_id = 1;
+        let mut category_ids = HashMap::new();
+        let mut categories = vec![];
+        for category in legacy.categories.iter() {
+            category_ids.insert(category.name.clone(), next_id);
+            categories.push(Category {
+                id: next_id,
+                name: category.name.clone(),
+                max_placements: category.max_placements,
+                min_placements: 0,
+                quota: None,
+                classes: vec![],
+                required_tags: vec![],
+                slots: vec![],
+            });
+            next_id += 1;
+        }
+
+        let category_ids_of = |categories: &mut dyn Iterator<Item = &LegacyCategory>| {
+            categories
+                .filter_map(|c| category_ids.get(&c.name).copied())
+                .collect::<Vec<Id>>()
+        };
+
+        let mut student_ids = HashMap::new();
+        let mut students = vec![];
+        for student in legacy.students.iter() {
+            student_ids.insert(student.name.clone(), next_id);
+            students.push(Student {
+                id: next_id,
+                name: student.name.clone(),
+                class: String::new(),
+                tags: vec![],
+                preferences: category_ids_of(&mut student.preferences.iter()),
+                exclude: category_ids_of(&mut student.exclude.iter()),
+                locked: None,
+                group: None,
+                separate: vec![],
+                max_activities: None,
+                priority: 0,
+            });
+            next_id += 1;
+        }
+
+        let student_ids_of = |students: &[LegacyStudent]| {
+            students
+                .iter()
+                .filter_map(|s| student_ids.get(&s.name).copied())
+                .collect::<Vec<Id>>()
+        };
+
+        let match_result = legacy.match_result.map(|match_result| {
+            RefCell::new(MatchResult {
+                placed: match_result
+                    .placed
+                    .iter()
+                    .filter_map(|(name, students)| {
+                        Some((*category_ids.get(name)?, student_ids_of(students)))
+                    })
+                    .collect(),
+                not_placable: student_ids_of(&match_result.not_placable),
+                seed: None,
+                tie_break: TieBreak::Single,
+                lottery: vec![],
+                category_lotteries: BTreeMap::new(),
+                priorities: BTreeMap::new(),
+                slots: vec![],
+                cancelled: vec![],
+                released: vec![],
+            })
+        });
+
+        State {
+            categories: RefCell::new(categories),
+            students: RefCell::new(students),
+            multi_matches: legacy.multi_matches,
+            rounds: 1,
+            max_activities: None,
+            tie_break: TieBreak::Single,
+            match_result,
+            next_id,
+        }
+    }
+}
+
+// Rounds that no longer exist are dropped from the activities. When that
+// would leave an activity without rounds, nothing is changed, as it would
+// then run in every round.
+pub fn set_rounds(categories: &mut [Category], rounds: usize) -> Result<(), String> {
+    if let Some(category) = categories
+        .iter()
+        .find(|c| !c.slots.is_empty() && c.slots.iter().all(|slot| *slot >= rounds))
+    {
+        return Err(format!(
+            "{} vindt alleen plaats in rondes die dan vervallen. Kies eerst een andere ronde voor deze activiteit.",
+            category.name
+        ));
+    }
+    for category in categories.iter_mut() {
+        category.slots.retain(|slot| *slot < rounds);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn legacy_state_is_converted_to_ids() {
+        let json = r#"{
+            "categories": [{"name": "Voetbal", "max_placements": 2}, {"name": "Tekenen", "max_placements": 1}],
+            "students": [
+                {"name": "Sam", "preferences": [{"name": "Tekenen", "max_placements": 1}], "exclude": [{"name": "Voetbal", "max_placements": 2}]},
+                {"name": "Noor", "preferences": [], "exclude": []}
+            ],
+            "multi_matches": false,
+            "match_result": {"placed": {"Tekenen": [{"name": "Sam", "preferences": [], "exclude": []}]}, "not_placable": [{"name": "Noor", "preferences": [], "exclude": []}]}
+        }"#;
+        let state: State = serde_json::from_str::<LegacyState>(json).unwrap().into();
+
+        let students = state.students.borrow();
+        assert_eq!(students[0].preferences, vec![2]);
+        assert_eq!(students[0].exclude, vec![1]);
+        let match_result = state.match_result.unwrap().into_inner();
+        assert_eq!(match_result.placed[&2], vec![students[0].id]);
+        assert_eq!(match_result.not_placable, vec![students[1].id]);
+        assert_eq!(state.next_id, 5);
+    }
+
+    #[test]
+    fn the_last_round_of_an_activity_can_not_be_switched_off() {
+        let mut category = Category::new("Koken", 10);
+        category.toggle_slot(0, 2).unwrap();
+        assert_eq!(category.slots, vec![1]);
+        assert!(category.toggle_slot(1, 2).is_err());
+        assert_eq!(category.slots, vec![1]);
+        assert!(!category.runs_in(0));
+
+        category.toggle_slot(0, 2).unwrap();
+        assert!(category.slots.is_empty());
+    }
+
+    #[test]
+    fn fewer_rounds_never_leave_an_activity_without_rounds() {
+        let mut categories = vec![Category::new("Koken", 10), Category::new("Dans", 10)];
+        categories[0].slots = vec![0, 2];
+        categories[1].slots = vec![2];
+        assert!(set_rounds(&mut categories, 2).is_err());
+        assert_eq!(categories[0].slots, vec![0, 2]);
+        assert_eq!(categories[1].slots, vec![2]);
+
+        categories[1].slots = vec![1, 2];
+        set_rounds(&mut categories, 2).unwrap();
+        assert_eq!(categories[0].slots, vec![0]);
+        assert_eq!(categories[1].slots, vec![1]);
+    }
+}
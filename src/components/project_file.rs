@@ -0,0 +1,315 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::model::LegacyState;
+use super::State;
+use serde::Serialize;
+use serde_json::Value;
+
+// Version 1 files predate the ids of students and categories.
+pub const FORMAT_VERSION: u64 = 2;
+pub const JSON_MIME_TYPE: &str = "application/json";
+
+#[derive(Serialize)]
+struct ProjectFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    state: &'a State,
+}
+
+pub fn to_json(state: &State) -> Result<Vec<u8>, String> {
+    serde_json::to_vec_pretty(&ProjectFile {
+        version: FORMAT_VERSION,
+        state,
+    })
+    .map_err(|e| format!("Het project kon niet worden opgeslagen: {}", e))
+}
+
+pub fn from_json(content: &[u8]) -> Result<State, String> {
+    let value: Value = serde_json::from_slice(content)
+        .map_err(|e| format!("Het bestand is geen geldig projectbestand: {}", e))?;
+    let invalid =
+        |e: serde_json::Error| format!("Het bestand is geen geldig projectbestand: {}", e);
+
+    let mut state: State = match value.get("version").and_then(|v| v.as_u64()) {
+        Some(1) => serde_json::from_value::<LegacyState>(value)
+            .map(State::from)
+            .map_err(invalid)?,
+        Some(FORMAT_VERSION) => serde_json::from_value(value).map_err(invalid)?,
+        Some(version) => {
+            return Err(format!(
+                "Dit projectbestand heeft versie {} en kan niet worden geopend. Alleen versie {} en ouder wordt ondersteund.",
+                version, FORMAT_VERSION
+            ))
+        }
+        None => {
+            return Err("Het bestand is geen projectbestand van Eerlijke Indeling.".to_string())
+        }
+    };
+
+    validate(&state)?;
+
+    let max_id = state
+        .categories
+        .borrow()
+        .iter()
+        .map(|c| c.id)
+        .chain(state.students.borrow().iter().map(|s| s.id))
+        .max()
+        .unwrap_or(0);
+    state.next_id = state.next_id.max(max_id + 1);
+    Ok(state)
+}
+
+fn validate(state: &State) -> Result<(), String> {
+    let categories = state.categories.borrow();
+    let students = state.students.borrow();
+
+    for (i, category) in categories.iter().enumerate() {
+        if category.name.trim().is_empty() || category.max_placements == 0 {
+            return Err(format!(
+                "Activiteit {} heeft geen naam of geen plekken.",
+                i + 1
+            ));
+        }
+        if categories[..i]
+            .iter()
+            .any(|c| c.name == category.name || c.id == category.id)
+        {
+            return Err(format!(
+                "De activiteit '{}' komt meerdere keren voor.",
+                category.name
+            ));
+        }
+        if category.min_placements > category.max_placements {
+            return Err(format!(
+                "De activiteit '{}' heeft een minimum dat groter is dan het aantal plekken.",
+                category.name
+            ));
+        }
+        if let Some(quota) = &category.quota {
+            if quota.percentage == 0 || quota.percentage > 100 {
+                return Err(format!(
+                    "De activiteit '{}' heeft een ongeldig percentage van {}%.",
+                    category.name, quota.percentage
+                ));
+            }
+        }
+    }
+
+    if state.max_activities == Some(0) {
+        return Err(
+            "Het maximum aantal activiteiten per leerling moet minstens 1 zijn.".to_string(),
+        );
+    }
+
+    let known_category = |id: &u32| categories.iter().any(|c| c.id == *id);
+    for (i, student) in students.iter().enumerate() {
+        if student.name.trim().is_empty() {
+            return Err(format!("Leerling {} heeft geen naam.", i + 1));
+        }
+        if student.max_activities == Some(0) {
+            return Err(format!(
+                "De leerling '{}' moet minstens 1 activiteit mogen doen.",
+                student.name
+            ));
+        }
+        if students[..i].iter().any(|s| s.id == student.id)
+            || categories.iter().any(|c| c.id == student.id)
+        {
+            return Err(format!(
+                "De leerling '{}' heeft een ongeldig nummer.",
+                student.name
+            ));
+        }
+        if student
+            .preferences
+            .iter()
+            .chain(student.exclude.iter())
+            .chain(student.locked.iter())
+            .any(|id| !known_category(id))
+        {
+            return Err(format!(
+                "De leerling '{}' verwijst naar een onbekende activiteit.",
+                student.name
+            ));
+        }
+    }
+
+    let known_student = |id: &u32| students.iter().any(|s| s.id == *id);
+    if let Some(student) = students
+        .iter()
+        .find(|s| s.separate.iter().any(|id| !known_student(id)))
+    {
+        return Err(format!(
+            "De leerling '{}' verwijst naar een onbekende leerling.",
+            student.name
+        ));
+    }
+
+    if let Some(match_result) = &state.match_result {
+        let match_result = match_result.borrow();
+        if match_result
+            .placed
+            .keys()
+            .chain(match_result.cancelled.iter().map(|c| &c.category_id))
+            .chain(match_result.category_lotteries.keys())
+            .any(|id| !known_category(id))
+        {
+            return Err("De indeling verwijst naar een onbekende activiteit.".to_string());
+        }
+        if match_result
+            .placed
+            .values()
+            .flatten()
+            .chain(match_result.not_placable.iter())
+            .chain(match_result.lottery.iter())
+            .chain(match_result.category_lotteries.values().flatten())
+            .chain(match_result.priorities.keys())
+            .chain(match_result.released.iter())
+            .any(|id| !known_student(id))
+        {
+            return Err("De indeling verwijst naar een onbekende leerling.".to_string());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::components::model::{Category, MatchResult, Quota, QuotaAttribute, Student};
+    use std::cell::RefCell;
+
+    fn state() -> State {
+        let mut category = Category::new("Koken", 10);
+        category.id = 1;
+        let mut student = Student::new("Sam", vec![1], vec![]);
+        student.id = 2;
+        State {
+            categories: RefCell::new(vec![category]),
+            students: RefCell::new(vec![student]),
+            match_result: Some(RefCell::new(MatchResult {
+                placed: vec![(1, vec![2])].into_iter().collect(),
+                lottery: vec![2],
+                ..MatchResult::default()
+            })),
+            next_id: 3,
+            ..State::default()
+        }
+    }
+
+    fn reopen(state: &State) -> Result<State, String> {
+        from_json(&to_json(state).unwrap())
+    }
+
+    #[test]
+    fn a_saved_project_opens_again() {
+        let state = state();
+        let reopened = reopen(&state).unwrap();
+        assert_eq!(reopened.categories, state.categories);
+        assert_eq!(reopened.students, state.students);
+        let placed = |state: &State| {
+            state
+                .match_result
+                .as_ref()
+                .map(|m| (m.borrow().placed.clone(), m.borrow().lottery.clone()))
+        };
+        assert_eq!(placed(&reopened), placed(&state));
+        assert_eq!(reopened.next_id, 3);
+    }
+
+    #[test]
+    fn the_version_must_be_known() {
+        assert!(from_json(br#"{"version": 3, "categories": []}"#)
+            .unwrap_err()
+            .contains("versie 3"));
+        assert!(from_json(br#"{"categories": [], "students": []}"#)
+            .unwrap_err()
+            .contains("geen projectbestand"));
+        assert!(from_json(b"geen json").is_err());
+    }
+
+    #[test]
+    fn unknown_activities_are_rejected() {
+        let state = state();
+        state.students.borrow_mut()[0].preferences = vec![1, 9];
+        assert!(reopen(&state).unwrap_err().contains("onbekende activiteit"));
+
+        let state = self::state();
+        if let Some(match_result) = &state.match_result {
+            match_result.borrow_mut().placed.insert(9, vec![]);
+        }
+        assert!(reopen(&state).unwrap_err().contains("onbekende activiteit"));
+    }
+
+    #[test]
+    fn unknown_students_are_rejected() {
+        let state = state();
+        state.students.borrow_mut()[0].separate = vec![9];
+        assert!(reopen(&state).unwrap_err().contains("onbekende leerling"));
+
+        let state = self::state();
+        if let Some(match_result) = &state.match_result {
+            match_result.borrow_mut().not_placable = vec![9];
+        }
+        assert!(reopen(&state).unwrap_err().contains("onbekende leerling"));
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+        let state = state();
+        let mut other = Category::new("Dans", 5);
+        other.id = 1;
+        state.categories.borrow_mut().push(other);
+        assert!(reopen(&state).unwrap_err().contains("meerdere keren"));
+
+        let state = self::state();
+        let mut other = Student::new("Noor", vec![], vec![]);
+        other.id = 2;
+        state.students.borrow_mut().push(other);
+        assert!(reopen(&state).unwrap_err().contains("ongeldig nummer"));
+
+        let state = self::state();
+        state.students.borrow_mut()[0].id = 1;
+        assert!(reopen(&state).is_err());
+    }
+
+    #[test]
+    fn invalid_limits_are_rejected() {
+        let state = state();
+        state.categories.borrow_mut()[0].min_placements = 11;
+        assert!(reopen(&state).unwrap_err().contains("minimum"));
+
+        let state = self::state();
+        state.categories.borrow_mut()[0].quota = Some(Quota {
+            attribute: QuotaAttribute::Class,
+            percentage: 150,
+            hard: true,
+        });
+        assert!(reopen(&state).unwrap_err().contains("percentage"));
+
+        let mut state = self::state();
+        state.max_activities = Some(0);
+        assert!(reopen(&state).is_err());
+
+        let state = self::state();
+        state.students.borrow_mut()[0].max_activities = Some(0);
+        assert!(reopen(&state).is_err());
+    }
+}
@@ -0,0 +1,252 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::projects::ProjectInfo;
+use wasm_bindgen::JsCast;
+use web_sys::{FormData, HtmlFormElement, HtmlInputElement};
+use yew::prelude::*;
+use yew::services::DialogService;
+
+pub struct State {
+    editing: Option<u32>,
+    name: String,
+    error: bool,
+}
+
+pub struct ProjectManager {
+    props: Props,
+    link: ComponentLink<Self>,
+    name_input_ref: NodeRef,
+    state: State,
+}
+
+pub enum Msg {
+    NewProject(FormData),
+    EditName(u32),
+    EditNameEnd,
+    SwitchProject(u32),
+    DuplicateProject(u32),
+    DeleteProject(u32),
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub projects: Vec<ProjectInfo>,
+    pub current: u32,
+    pub on_new_project: Callback<String>,
+    pub on_switch_project: Callback<u32>,
+    pub on_rename_project: Callback<(u32, String)>,
+    pub on_duplicate_project: Callback<u32>,
+    pub on_delete_project: Callback<u32>,
+}
+
+impl Component for ProjectManager {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            name_input_ref: NodeRef::default(),
+            state: State {
+                editing: None,
+                name: "".into(),
+                error: false,
+            },
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::NewProject(form_data) => {
+                let name = form_data
+                    .get("project_name")
+                    .as_string()
+                    .unwrap_or_default();
+                if name.trim().is_empty() {
+                    self.state.name = name;
+                    self.state.error = true;
+                } else {
+                    self.props.on_new_project.emit(name);
+                    self.state.name = "".into();
+                    self.state.error = false;
+                }
+                true
+            }
+            Msg::EditName(id) => {
+                self.state.editing = Some(id);
+                true
+            }
+            Msg::EditNameEnd => {
+                if let Some(id) = self.state.editing.take() {
+                    if let Some(input) = self.name_input_ref.cast::<HtmlInputElement>() {
+                        if !input.value().trim().is_empty() {
+                            self.props.on_rename_project.emit((id, input.value()));
+                        }
+                    }
+                }
+                true
+            }
+            Msg::SwitchProject(id) => {
+                self.props.on_switch_project.emit(id);
+                false
+            }
+            Msg::DuplicateProject(id) => {
+                self.props.on_duplicate_project.emit(id);
+                false
+            }
+            Msg::DeleteProject(id) => {
+                let name = self
+                    .props
+                    .projects
+                    .iter()
+                    .find(|p| p.id == id)
+                    .map(|p| p.name.clone())
+                    .unwrap_or_default();
+                if DialogService::confirm(&format!(
+                    "Weet je zeker dat je het project '{}' wilt verwijderen? Dit is niet ongedaan te maken.",
+                    name
+                )) {
+                    self.props.on_delete_project.emit(id);
+                }
+                false
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let projects: Vec<Html> = self
+            .props
+            .projects
+            .iter()
+            .map(|project| self.view_project(project))
+            .collect();
+
+        let submit_form = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::NewProject(
+                FormData::new_with_form(
+                    &e.target()
+                        .and_then(|t| t.dyn_into::<HtmlFormElement>().ok())
+                        .expect("project form"),
+                )
+                .expect("FormData can not be created"),
+            )
+        });
+
+        html! {
+            <>
+                <table class="table table-sm">
+                    <tbody>
+                        { projects }
+                    </tbody>
+                </table>
+                <form class="form-inline" novalidate=true onsubmit=submit_form>
+                    <div class="form-group mb-2 mr-sm-3">
+                        <input type="text" class="form-control" name="project_name" placeholder="Naam nieuw project" value=&self.state.name />
+                    </div>
+                    <button type="submit" class="btn btn-primary mb-2">{ "Nieuw project" }</button>
+                    {
+                        if self.state.error {
+                            html! { <div class="invalid-feedback d-block">{ "Een naam is noodzakelijk!" }</div> }
+                        } else {
+                            html! {}
+                        }
+                    }
+                </form>
+            </>
+        }
+    }
+
+    fn rendered(&mut self, _first_render: bool) {
+        if let Some(input) = self.name_input_ref.cast::<HtmlInputElement>() {
+            input.focus().unwrap_or(());
+        }
+    }
+}
+
+impl ProjectManager {
+    fn view_project(&self, project: &ProjectInfo) -> Html {
+        let id = project.id;
+        let handle_switch = self.link.callback(move |e: MouseEvent| {
+            e.prevent_default();
+            Msg::SwitchProject(id)
+        });
+        let handle_edit_name = self.link.callback(move |e: MouseEvent| {
+            e.prevent_default();
+            Msg::EditName(id)
+        });
+        let handle_edit_name_end = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::EditNameEnd
+        });
+        let handle_edit_name_submit = self.link.callback(|e: FocusEvent| {
+            e.prevent_default();
+            Msg::EditNameEnd
+        });
+        let handle_duplicate = self.link.callback(move |e: MouseEvent| {
+            e.prevent_default();
+            Msg::DuplicateProject(id)
+        });
+        let handle_delete = self.link.callback(move |e: MouseEvent| {
+            e.prevent_default();
+            Msg::DeleteProject(id)
+        });
+        let is_current = project.id == self.props.current;
+        let editing = self.state.editing == Some(project.id);
+
+        html! {
+            <tr>
+                <td>
+                    {
+                        if editing {
+                            html! {
+                                <form class="inline" onsubmit=handle_edit_name_submit>
+                                    <input type="text" name="project_name" value=&project.name ref=self.name_input_ref.clone() />
+                                </form>
+                            }
+                        } else if is_current {
+                            html! { <strong>{ &project.name }</strong> }
+                        } else {
+                            html! { &project.name }
+                        }
+                    }
+                </td>
+                <td class="text-right">
+                    <form class="inline">
+                        <button class="btn btn-success btn-sm mr-1" disabled=is_current onclick=handle_switch>{ "Openen" }</button>
+                        {
+                            if editing {
+                                html! { <button class="btn btn-info btn-sm mr-1" onclick=handle_edit_name_end>{ "Opslaan" }</button> }
+                            } else {
+                                html! { <button class="btn btn-info btn-sm mr-1" disabled=self.state.editing.is_some() onclick=handle_edit_name>{ "Hernoem" }</button> }
+                            }
+                        }
+                        <button class="btn btn-secondary btn-sm mr-1" onclick=handle_duplicate>{ "Kopieer" }</button>
+                        <button class="btn btn-danger btn-sm" onclick=handle_delete>{ "Verwijder" }</button>
+                    </form>
+                </td>
+            </tr>
+        }
+    }
+}
@@ -0,0 +1,136 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::model::LegacyState;
+use super::State;
+use serde::{Deserialize, Serialize};
+use yew::format::Json;
+use yew::services::storage::StorageService;
+
+const LEGACY_KEY: &str = "fair_assignment.components.main";
+const INDEX_KEY: &str = "fair_assignment.projects";
+const DEFAULT_PROJECT_NAME: &str = "Mijn indeling";
+
+#[derive(Deserialize, Serialize, Clone, PartialEq, Debug)]
+pub struct ProjectInfo {
+    pub id: u32,
+    pub name: String,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ProjectIndex {
+    pub projects: Vec<ProjectInfo>,
+    pub current: u32,
+    next_id: u32,
+}
+
+fn project_key(id: u32) -> String {
+    format!("{}.{}", INDEX_KEY, id)
+}
+
+impl ProjectIndex {
+    pub fn restore(storage: &mut StorageService) -> Self {
+        if let Json(Ok(index)) = storage.restore(INDEX_KEY) {
+            let index: ProjectIndex = index;
+            if !index.projects.is_empty() {
+                return index;
+            }
+        }
+
+        // Before projects existed everything was stored under a single key.
+        let mut index = ProjectIndex {
+            projects: vec![],
+            current: 0,
+            next_id: 1,
+        };
+        let id = index.create(DEFAULT_PROJECT_NAME);
+        index.current = id;
+        if let Json(Ok(state)) = storage.restore::<Json<Result<LegacyState, _>>>(LEGACY_KEY) {
+            let state = State::from(state);
+            log::info!("Migrating stored state to project {}", id);
+            store_state(storage, id, &state);
+            storage.remove(LEGACY_KEY);
+        }
+        index.store(storage);
+        index
+    }
+
+    pub fn store(&self, storage: &mut StorageService) {
+        storage.store(INDEX_KEY, Json(self));
+    }
+
+    pub fn current_name(&self) -> &str {
+        self.projects
+            .iter()
+            .find(|p| p.id == self.current)
+            .map(|p| p.name.as_str())
+            .unwrap_or("")
+    }
+
+    pub fn create(&mut self, name: &str) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.projects.push(ProjectInfo {
+            id,
+            name: name.trim().to_string(),
+        });
+        id
+    }
+
+    pub fn rename(&mut self, id: u32, name: &str) {
+        if let Some(project) = self.projects.iter_mut().find(|p| p.id == id) {
+            project.name = name.trim().to_string();
+        }
+    }
+
+    pub fn duplicate(&mut self, storage: &mut StorageService, id: u32) -> Option<u32> {
+        let name = self.projects.iter().find(|p| p.id == id)?.name.clone();
+        let state = restore_state(storage, id);
+        let new_id = self.create(&format!("{} (kopie)", name));
+        store_state(storage, new_id, &state);
+        Some(new_id)
+    }
+
+    // Removing the last project leaves a fresh, empty one behind.
+    pub fn delete(&mut self, storage: &mut StorageService, id: u32) {
+        self.projects.retain(|p| p.id != id);
+        storage.remove(&project_key(id));
+        if self.projects.is_empty() {
+            self.create(DEFAULT_PROJECT_NAME);
+        }
+        if self.current == id {
+            self.current = self.projects[0].id;
+        }
+    }
+}
+
+pub fn restore_state(storage: &StorageService, id: u32) -> State {
+    if let Json(Ok(state)) = storage.restore(&project_key(id)) {
+        state
+    } else if let Json(Ok(legacy)) =
+        storage.restore::<Json<Result<LegacyState, _>>>(&project_key(id))
+    {
+        log::info!("Migrating project {} to ids", id);
+        State::from(legacy)
+    } else {
+        State::default()
+    }
+}
+
+pub fn store_state(storage: &mut StorageService, id: u32, state: &State) {
+    storage.store(&project_key(id), Json(state));
+}
@@ -0,0 +1,185 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::export::{placed_students, preference_rank};
+use super::model::{category_by_id, Category, Id, MatchResult, Student};
+use std::cell::RefCell;
+use yew::prelude::*;
+
+pub struct CategoryFill {
+    pub category_id: Id,
+    pub placed: usize,
+    pub max_placements: usize,
+}
+
+pub struct Statistics {
+    // Index 0 holds the number of placements in a first choice, and so on.
+    pub rank_counts: Vec<usize>,
+    pub outside_preferences: usize,
+    pub not_placable: usize,
+    pub students: usize,
+    // A student in several activities counts once for every activity.
+    pub placements: usize,
+    pub fill: Vec<CategoryFill>,
+}
+
+impl Statistics {
+    pub fn new(match_result: &MatchResult, students: &[Student], categories: &[Category]) -> Self {
+        let mut statistics = Statistics {
+            rank_counts: vec![],
+            outside_preferences: 0,
+            not_placable: students
+                .iter()
+                .filter(|s| match_result.not_placable.contains(&s.id))
+                .count(),
+            students: students.len(),
+            placements: 0,
+            fill: vec![],
+        };
+
+        for category in categories {
+            let placed = placed_students(match_result, students, category.id);
+            for student in placed.iter() {
+                match preference_rank(student, category.id) {
+                    Some(rank) => {
+                        if statistics.rank_counts.len() < rank {
+                            statistics.rank_counts.resize(rank, 0);
+                        }
+                        statistics.rank_counts[rank - 1] += 1;
+                    }
+                    None => statistics.outside_preferences += 1,
+                }
+            }
+            statistics.placements += placed.len();
+            statistics.fill.push(CategoryFill {
+                category_id: category.id,
+                placed: placed.len(),
+                max_placements: category.total_places(match_result.slots.len()),
+            });
+        }
+
+        statistics
+    }
+}
+
+fn percentage(count: usize, total: usize) -> String {
+    if total == 0 {
+        "-".to_string()
+    } else {
+        format!("{:.0}%", count as f64 * 100.0 / total as f64)
+    }
+}
+
+pub struct StatisticsBlock {
+    props: Props,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub match_result: RefCell<MatchResult>,
+    pub categories: RefCell<Vec<Category>>,
+    pub students: RefCell<Vec<Student>>,
+}
+
+impl Component for StatisticsBlock {
+    type Message = ();
+    type Properties = Props;
+
+    fn create(props: Self::Properties, _: ComponentLink<Self>) -> Self {
+        Self { props }
+    }
+
+    fn update(&mut self, _: Self::Message) -> ShouldRender {
+        false
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let categories = self.props.categories.borrow();
+        let statistics = Statistics::new(
+            &self.props.match_result.borrow(),
+            &self.props.students.borrow(),
+            &categories,
+        );
+
+        html! {
+            <div class="row">
+                <div class="col shadow p-3 mb-5 bg-white rounded">
+                    <h3>{ "Statistieken" }</h3>
+                    <div class="row">
+                        <div class="col-md-6">
+                            <h5>{ "Voorkeuren" }</h5>
+                            <table class="table table-sm">
+                                <tr>
+                                    <th>{ "Ingedeeld in" }</th>
+                                    <th class="text-right">{ "Plaatsingen" }</th>
+                                    <th class="text-right">{ "% van de plaatsingen" }</th>
+                                </tr>
+                                <tbody>
+                                {
+                                    statistics.rank_counts.iter().enumerate().map(|(index, count)| {
+                                        html! {
+                                            <tr>
+                                                <td>{ format!("{}e keuze", index + 1) }</td>
+                                                <td class="text-right">{ count }</td>
+                                                <td class="text-right">{ percentage(*count, statistics.placements) }</td>
+                                            </tr>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                                <tr>
+                                    <td>{ "Buiten de voorkeuren" }</td>
+                                    <td class="text-right">{ statistics.outside_preferences }</td>
+                                    <td class="text-right">{ percentage(statistics.outside_preferences, statistics.placements) }</td>
+                                </tr>
+                                </tbody>
+                            </table>
+                            <p>{ format!("Niet ingedeeld: {} van de {} leerlingen ({})", statistics.not_placable, statistics.students, percentage(statistics.not_placable, statistics.students)) }</p>
+                        </div>
+                        <div class="col-md-6">
+                            <h5>{ "Bezetting per activiteit" }</h5>
+                            <table class="table table-sm">
+                                <tr>
+                                    <th>{ "Activiteit" }</th>
+                                    <th class="text-right">{ "Ingedeeld" }</th>
+                                    <th class="text-right">{ "Bezetting" }</th>
+                                </tr>
+                                <tbody>
+                                {
+                                    statistics.fill.iter().filter_map(|fill| Some((fill, category_by_id(&categories, fill.category_id)?))).map(|(fill, category)| {
+                                        html! {
+                                            <tr>
+                                                <td>{ &category.name }</td>
+                                                <td class="text-right">{ format!("{} / {}", fill.placed, fill.max_placements) }</td>
+                                                <td class="text-right">{ percentage(fill.placed, fill.max_placements) }</td>
+                                            </tr>
+                                        }
+                                    }).collect::<Html>()
+                                }
+                                </tbody>
+                            </table>
+                        </div>
+                    </div>
+                </div>
+            </div>
+        }
+    }
+}
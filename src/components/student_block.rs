@@ -15,21 +15,24 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
+use super::model::{classes, Category, Id, Student};
+use super::student_import::StudentImport;
 use super::student_row::StudentRow;
-use matchmaker::{Category, Student};
 use std::cell::RefCell;
-use std::collections::VecDeque;
+use std::collections::BTreeMap;
 use wasm_bindgen::JsCast;
 use web_sys::{FormData, HtmlFormElement, HtmlInputElement};
 use yew::prelude::*;
 
 pub enum FieldError {
-    Duplicate,
     Empty,
 }
 
 pub struct State {
     name: String,
+    // Kept after adding a student, as students are usually entered per class.
+    class: String,
+    class_filter: Option<String>,
     error: Option<FieldError>,
 }
 
@@ -42,6 +45,7 @@ pub struct StudentBlock {
 
 pub enum Msg {
     AddStudent(FormData),
+    SetClassFilter(ChangeData),
 }
 
 #[derive(Properties, Clone)]
@@ -49,13 +53,24 @@ pub struct Props {
     pub students: RefCell<Vec<Student>>,
     pub categories: RefCell<Vec<Category>>,
     pub on_add_student: Callback<Student>,
-    pub on_add_preference: Callback<(Student, Category)>,
-    pub on_add_exclude: Callback<(Student, Category)>,
-    pub on_move_preference: Callback<(Student, Category, Category)>,
-    pub on_remove_preference: Callback<(Student, Category)>,
-    pub on_remove_exclude: Callback<(Student, Category)>,
-    pub on_edit_student: Callback<(String, String)>,
-    pub on_remove_student: Callback<Student>,
+    pub on_add_students: Callback<Vec<Student>>,
+    pub on_add_preference: Callback<(Id, Id)>,
+    pub on_add_exclude: Callback<(Id, Id)>,
+    pub on_move_preference: Callback<(Id, Id, Id)>,
+    pub on_end_move_preference: Callback<()>,
+    pub on_remove_preference: Callback<(Id, Id)>,
+    pub on_remove_exclude: Callback<(Id, Id)>,
+    pub on_edit_student: Callback<(Id, String, String)>,
+    pub on_remove_student: Callback<Id>,
+    pub on_lock_student: Callback<(Id, Option<Id>)>,
+    pub on_set_group: Callback<(Id, Option<u32>)>,
+    pub on_add_separation: Callback<(Id, Id)>,
+    pub on_remove_separation: Callback<(Id, Id)>,
+    pub multi_matches: bool,
+    pub max_activities: Option<usize>,
+    pub on_set_max_activities: Callback<(Id, Option<usize>)>,
+    pub on_set_priority: Callback<(Id, u32)>,
+    pub on_set_tags: Callback<(Id, Vec<String>)>,
     pub editing: bool,
     pub on_editing: Callback<()>,
 }
@@ -71,6 +86,8 @@ impl Component for StudentBlock {
             name_input_ref: NodeRef::default(),
             state: State {
                 name: "".into(),
+                class: "".into(),
+                class_filter: None,
                 error: None,
             },
         }
@@ -79,26 +96,33 @@ impl Component for StudentBlock {
     fn update(&mut self, message: Self::Message) -> ShouldRender {
         match message {
             Msg::AddStudent(form_data) => {
-                let name = form_data.get("student_name").as_string().map(|n| n);
+                let name = form_data.get("student_name").as_string();
+                self.state.class = form_data
+                    .get("student_class")
+                    .as_string()
+                    .unwrap_or("".to_string());
                 if let Some(name) = name {
                     self.state.name = name.clone();
-                    if name != "" {
-                        let student = Student::new(name.trim(), VecDeque::new(), Vec::new());
-                        if self.props.students.borrow().contains(&student) {
-                            self.state.error = Some(FieldError::Duplicate);
-                        } else {
-                            self.props.on_add_student.emit(student);
-                            self.focus_on_input();
-                            self.state.name = "".into();
-                            self.state.error = None;
-                            return false;
-                        }
+                    if !name.trim().is_empty() {
+                        let mut student = Student::new(name.trim(), Vec::new(), Vec::new());
+                        student.class = self.state.class.trim().to_string();
+                        self.props.on_add_student.emit(student);
+                        self.focus_on_input();
+                        self.state.name = "".into();
+                        self.state.error = None;
+                        return false;
                     } else {
                         self.state.error = Some(FieldError::Empty);
                     }
                 }
                 true
             }
+            Msg::SetClassFilter(ChangeData::Select(select)) => {
+                let class = select.value();
+                self.state.class_filter = if class.is_empty() { None } else { Some(class) };
+                true
+            }
+            Msg::SetClassFilter(_) => false,
         }
     }
 
@@ -108,14 +132,43 @@ impl Component for StudentBlock {
     }
 
     fn view(&self) -> Html {
+        let mut groups: BTreeMap<u32, Vec<String>> = BTreeMap::new();
+        for student in self.props.students.borrow().iter() {
+            if let Some(group) = student.group {
+                groups.entry(group).or_default().push(student.name.clone());
+            }
+        }
+        let groups: Vec<(u32, String)> = groups
+            .into_iter()
+            .map(|(group, names)| (group, names.join(", ")))
+            .collect();
+
+        let classes = classes(&self.props.students.borrow());
+        let handle_set_class_filter = self.link.callback(Msg::SetClassFilter);
+
+        let student_names: Vec<(Id, String)> = self
+            .props
+            .students
+            .borrow()
+            .iter()
+            .map(|s| (s.id, s.name.clone()))
+            .collect();
+
         let students: Vec<Html> = self
             .props
             .students
             .borrow()
             .iter()
+            .filter(|student| {
+                self.state
+                    .class_filter
+                    .as_ref()
+                    .map(|class| *class == student.class)
+                    .unwrap_or(true)
+            })
             .map(|student: &Student| {
                 html! {
-                    <StudentRow student=student categories=self.props.categories.clone() editing=self.props.editing on_editing=self.props.on_editing.clone() on_add_preference=self.props.on_add_preference.clone() on_add_exclude=self.props.on_add_exclude.clone() on_move_preference=self.props.on_move_preference.clone() on_remove_preference=self.props.on_remove_preference.clone() on_remove_exclude=self.props.on_remove_exclude.clone() on_edit_student=self.props.on_edit_student.clone() on_remove_student=self.props.on_remove_student.clone() />
+                    <StudentRow student=student categories=self.props.categories.clone() editing=self.props.editing on_editing=self.props.on_editing.clone() on_add_preference=self.props.on_add_preference.clone() on_add_exclude=self.props.on_add_exclude.clone() on_move_preference=self.props.on_move_preference.clone() on_end_move_preference=self.props.on_end_move_preference.clone() on_remove_preference=self.props.on_remove_preference.clone() on_remove_exclude=self.props.on_remove_exclude.clone() on_edit_student=self.props.on_edit_student.clone() on_remove_student=self.props.on_remove_student.clone() on_lock_student=self.props.on_lock_student.clone() groups=groups.clone() on_set_group=self.props.on_set_group.clone() student_names=student_names.clone() on_add_separation=self.props.on_add_separation.clone() on_remove_separation=self.props.on_remove_separation.clone() multi_matches=self.props.multi_matches max_activities=self.props.max_activities on_set_max_activities=self.props.on_set_max_activities.clone() on_set_priority=self.props.on_set_priority.clone() on_set_tags=self.props.on_set_tags.clone() />
                 }
             })
             .collect();
@@ -138,9 +191,9 @@ impl Component for StudentBlock {
                         .unwrap()
                         .clone(),
                 )
-                .or_else(|e| {
+                .map_err(|e| {
                     log::info!("FormData can not be created: {:?}", e);
-                    Err(e)
+                    e
                 })
                 .unwrap(),
             )
@@ -150,13 +203,49 @@ impl Component for StudentBlock {
             <div class="col shadow p-3 mb-5 bg-white rounded">
                 <h2>{"Stap 2: Leerlingen toevoegen"}</h2>
                 <p>{ "Vermeld bij 'Naam leerling' de namen van de leerlingen die aan de activiteit willen deelnemen. Nadat je een leerling hebt toegevoegd, kun je de de eerste, tweede en opvolgende voorkeuren voor activiteiten selecteren uit de lijst naast diens naam." }</p>
+                <p>{ "Vul eventueel ook de 'Klas' in. Je kunt de leerlingen en de indeling dan per klas bekijken en afdrukken." }</p>
                 <p>{ "Het systeem probeert leerlingen eerst bij de activiteiten van hun voorkeur in te delen. Wanneer dat niet mogelijk blijkt, wordt de leerling ingedeeld bij een willekeurige activiteit waar wel plek is." }</p>
                 <p>{ "Geef bij 'Uitsluitingen' de activiteiten aan waar de leerling niet aan wil deelnemen. De leerling zal in dat geval niet bij deze activiteiten worden ingedeeld." }</p>
+                <p>{ "Willen leerlingen samen bij dezelfde activiteit? Zet ze dan in hetzelfde 'Groepje'. Een groepje krijgt één lotnummer en wordt ingedeeld op basis van de gecombineerde voorkeuren van de leden. Lukt het niet om een groepje bij elkaar te houden, dan staat dat bij de indeling." }</p>
+                <p>{ "Bij 'Niet samen met' kies je leerlingen die nooit bij dezelfde activiteit mogen worden ingedeeld. De leerling met het slechtste lotnummer gaat dan door naar een volgende voorkeur." }</p>
+                <p>{ "Kreeg een leerling de vorige keer een lage voorkeur? Geef deze dan 'Voorrang'. Leerlingen met voorrang 1 loten vóór alle leerlingen zonder voorrang, leerlingen met voorrang 2 vóór die met voorrang 1, enzovoort." }</p>
+                {
+                    if !classes.is_empty() {
+                        html! {
+                            <form class="form-inline mb-3">
+                                <label class="mr-2" for="student_class_filter">{ "Toon klas" }</label>
+                                <select class="form-control form-control-sm" id="student_class_filter" onchange=handle_set_class_filter>
+                                    <option value="" selected=self.state.class_filter.is_none()>{ "Alle klassen" }</option>
+                                    {
+                                        classes.iter().map(|class| {
+                                            html! { <option value=class selected=self.state.class_filter.as_ref() == Some(class)>{ class }</option> }
+                                        }).collect::<Html>()
+                                    }
+                                </select>
+                            </form>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <table class="table table-responsive-sm">
                     <tr>
                         <th>{ "Naam leerling" }</th>
+                        <th>{ "Klas" }</th>
+                        <th title="Kenmerken die activiteiten kunnen vereisen, zoals 'zwemdiploma', gescheiden door komma's.">{ "Kenmerken" }</th>
                         <th>{ "Voorkeuren" }</th>
                         <th>{ "Uitsluitingen" }</th>
+                        <th>{ "Vaste plek" }</th>
+                        <th>{ "Groepje" }</th>
+                        <th>{ "Niet samen met" }</th>
+                        <th title="Leerlingen met een hogere voorrang krijgen een beter lotnummer dan alle leerlingen met een lagere voorrang.">{ "Voorrang" }</th>
+                        {
+                            if self.props.multi_matches {
+                                html! { <th title="Het maximum aantal activiteiten voor deze leerling.">{ "Max." }</th> }
+                            } else {
+                                html! {}
+                            }
+                        }
                         <th class="control"></th>
                     </tr>
                     <tbody>
@@ -169,13 +258,15 @@ impl Component for StudentBlock {
                     <div class="form-group mb-2">
                         <input type="text" disabled=self.props.editing class="form-control" name="student_name" id="student_name" placeholder="Naam leerling" value=&self.state.name ref=self.name_input_ref.clone() />
                     </div>
+                    <div class="form-group mb-2 mx-sm-3">
+                        <input type="text" disabled=self.props.editing class="form-control" name="student_class" id="student_class" placeholder="Klas (optioneel)" value=&self.state.class />
+                    </div>
                     <button type="submit" class="btn btn-primary mb-2" disabled=self.props.editing>{ "Toevoegen" }</button>
                     { if self.state.error.is_some() {
                         html! {
                             <div class="invalid-feedback d-block">
                                 {
                                     match self.state.error {
-                                        Some(FieldError::Duplicate) => "Er bestaat al een leerling met deze naam!",
                                         Some(FieldError::Empty) => "Een naam is noodzakelijk!",
                                         _ => "Onbekende fout",
                                     }
@@ -186,6 +277,7 @@ impl Component for StudentBlock {
                         html! {}
                     } }
                 </form>
+                <StudentImport students=self.props.students.clone() categories=self.props.categories.clone() on_add_students=self.props.on_add_students.clone() editing=self.props.editing />
             </div>
         }
     }
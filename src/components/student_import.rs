@@ -0,0 +1,516 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use super::import::{read_rows, Row};
+use super::model::{category_by_id, parse_list, Category, Id, Student};
+use std::cell::RefCell;
+use web_sys::HtmlInputElement;
+use yew::prelude::*;
+use yew::services::reader::{FileData, ReaderService, ReaderTask};
+
+enum Column {
+    Name,
+    Class,
+    Priority,
+    Tags,
+    Preference,
+    Exclude,
+    Ignore,
+}
+
+pub struct Preview {
+    students: Vec<Student>,
+    unknown_categories: Vec<(u64, String)>,
+    duplicate_names: Vec<(u64, String)>,
+    invalid_priorities: Vec<(u64, String)>,
+    empty_rows: Vec<u64>,
+}
+
+pub struct State {
+    preview: Option<Preview>,
+    error: Option<String>,
+}
+
+pub struct StudentImport {
+    props: Props,
+    link: ComponentLink<Self>,
+    reader: ReaderService,
+    reader_task: Option<ReaderTask>,
+    file_input_ref: NodeRef,
+    state: State,
+}
+
+pub enum Msg {
+    SelectFile(ChangeData),
+    FileLoaded(FileData),
+    Import,
+    Cancel,
+}
+
+#[derive(Properties, Clone)]
+pub struct Props {
+    pub students: RefCell<Vec<Student>>,
+    pub categories: RefCell<Vec<Category>>,
+    pub on_add_students: Callback<Vec<Student>>,
+    pub editing: bool,
+}
+
+impl Component for StudentImport {
+    type Message = Msg;
+    type Properties = Props;
+
+    fn create(props: Self::Properties, link: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            link,
+            reader: ReaderService::new(),
+            reader_task: None,
+            file_input_ref: NodeRef::default(),
+            state: State {
+                preview: None,
+                error: None,
+            },
+        }
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Msg::SelectFile(ChangeData::Files(files)) => {
+                if let Some(file) = files.get(0) {
+                    let callback = self.link.callback(Msg::FileLoaded);
+                    match self.reader.read_file(file, callback) {
+                        Ok(task) => self.reader_task = Some(task),
+                        Err(e) => {
+                            log::error!("Could not read file: {:?}", e);
+                            self.state.error = Some("Het bestand kon niet worden gelezen.".into());
+                        }
+                    }
+                }
+                true
+            }
+            Msg::SelectFile(_) => false,
+            Msg::FileLoaded(file_data) => {
+                log::info!("Importing students from {:?}", &file_data.name);
+                self.reader_task = None;
+                let content = String::from_utf8_lossy(&file_data.content);
+                match read_rows(&content) {
+                    Ok(rows) => {
+                        self.state.preview = Some(build_preview(
+                            rows,
+                            &self.props.students.borrow(),
+                            &self.props.categories.borrow(),
+                        ));
+                        self.state.error = None;
+                    }
+                    Err(error) => {
+                        self.state.preview = None;
+                        self.state.error = Some(error);
+                    }
+                }
+                true
+            }
+            Msg::Import => {
+                if let Some(preview) = self.state.preview.take() {
+                    self.props.on_add_students.emit(preview.students);
+                }
+                self.clear_file_input();
+                true
+            }
+            Msg::Cancel => {
+                self.state.preview = None;
+                self.state.error = None;
+                self.clear_file_input();
+                true
+            }
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        self.props = props;
+        true
+    }
+
+    fn view(&self) -> Html {
+        let handle_select_file = self.link.callback(Msg::SelectFile);
+
+        html! {
+            <>
+                <h5 class="mt-4">{ "Leerlingen importeren" }</h5>
+                <p>{ "Heb je de voorkeuren al in een spreadsheet staan? Sla deze dan op als CSV-bestand met in de eerste kolom de naam van de leerling, gevolgd door eventueel kolommen 'Klas', 'Voorrang' en 'Kenmerken', kolommen 'Voorkeur 1', 'Voorkeur 2', enzovoort en eventueel kolommen 'Uitsluiting 1', 'Uitsluiting 2', enzovoort. De namen van de activiteiten moeten overeenkomen met de activiteiten uit stap 1." }</p>
+                <form class="form-inline">
+                    <div class="form-group mb-2">
+                        <input type="file" class="form-control-file" accept=".csv,.txt,text/csv" disabled=self.props.editing onchange=handle_select_file ref=self.file_input_ref.clone() />
+                    </div>
+                </form>
+                {
+                    if let Some(error) = &self.state.error {
+                        html! { <div class="invalid-feedback d-block">{ error }</div> }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if let Some(preview) = &self.state.preview {
+                        self.view_preview(preview)
+                    } else {
+                        html! {}
+                    }
+                }
+            </>
+        }
+    }
+}
+
+impl StudentImport {
+    fn view_preview(&self, preview: &Preview) -> Html {
+        let handle_import = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Import
+        });
+        let handle_cancel = self.link.callback(|e: MouseEvent| {
+            e.prevent_default();
+            Msg::Cancel
+        });
+
+        let categories = self.props.categories.borrow();
+        let category_names = |ids: &[Id]| {
+            ids.iter()
+                .filter_map(|id| category_by_id(&categories, *id))
+                .map(|c| c.name.clone())
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        html! {
+            <div class="border rounded p-3 mt-3">
+                <h5>{ "Voorbeeld van de import" }</h5>
+                {
+                    if !preview.unknown_categories.is_empty() {
+                        html! {
+                            <div class="alert alert-warning">
+                                <p>{ "De volgende activiteiten zijn niet gevonden en worden overgeslagen:" }</p>
+                                <ul>
+                                {
+                                    preview.unknown_categories.iter().map(|(line, name)| {
+                                        html! { <li>{ format!("Regel {}: {}", line, name) }</li> }
+                                    }).collect::<Html>()
+                                }
+                                </ul>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if !preview.duplicate_names.is_empty() {
+                        html! {
+                            <div class="alert alert-warning">
+                                <p>{ "De volgende leerlingen bestaan al in dezelfde klas en worden niet nogmaals toegevoegd:" }</p>
+                                <ul>
+                                {
+                                    preview.duplicate_names.iter().map(|(line, name)| {
+                                        html! { <li>{ format!("Regel {}: {}", line, name) }</li> }
+                                    }).collect::<Html>()
+                                }
+                                </ul>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if !preview.invalid_priorities.is_empty() {
+                        html! {
+                            <div class="alert alert-warning">
+                                <p>{ "De volgende voorrang is geen geheel getal, deze leerlingen krijgen geen voorrang:" }</p>
+                                <ul>
+                                {
+                                    preview.invalid_priorities.iter().map(|(line, value)| {
+                                        html! { <li>{ format!("Regel {}: {}", line, value) }</li> }
+                                    }).collect::<Html>()
+                                }
+                                </ul>
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                {
+                    if !preview.empty_rows.is_empty() {
+                        html! {
+                            <div class="alert alert-warning">
+                                {
+                                    format!("Regels zonder naam worden overgeslagen: {}", preview.empty_rows.iter().map(|l| l.to_string()).collect::<Vec<String>>().join(", "))
+                                }
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                <table class="table table-sm table-responsive-sm">
+                    <tr>
+                        <th>{ "Naam leerling" }</th>
+                        <th>{ "Klas" }</th>
+                        <th>{ "Voorrang" }</th>
+                        <th>{ "Voorkeuren" }</th>
+                        <th>{ "Uitsluitingen" }</th>
+                    </tr>
+                    <tbody>
+                    {
+                        preview.students.iter().map(|student| {
+                            html! {
+                                <tr>
+                                    <td>{ &student.name }</td>
+                                    <td>{ &student.class }</td>
+                                    <td>{ student.priority }</td>
+                                    <td>{ category_names(&student.preferences) }</td>
+                                    <td>{ category_names(&student.exclude) }</td>
+                                </tr>
+                            }
+                        }).collect::<Html>()
+                    }
+                    </tbody>
+                </table>
+                <button class="btn btn-primary mr-2" onclick=handle_import disabled=preview.students.is_empty()>{ format!("{} leerlingen importeren", preview.students.len()) }</button>
+                <button class="btn btn-secondary" onclick=handle_cancel>{ "Annuleren" }</button>
+            </div>
+        }
+    }
+
+    fn clear_file_input(&self) {
+        if let Some(input) = self.file_input_ref.cast::<HtmlInputElement>() {
+            input.set_value("");
+        }
+    }
+}
+
+// Only a cell that is exactly a known column name counts, apart from a
+// number, so "Voorkeur 2" is a preference column. A row with a student
+// named "Klaske" is not taken for a header.
+fn column_type(header: &str) -> Column {
+    let header = header.to_lowercase();
+    match header
+        .trim_end_matches(|c: char| c.is_ascii_digit() || c.is_whitespace() || c == '.')
+        .trim()
+    {
+        "naam" | "naam leerling" | "leerling" | "name" | "student" => Column::Name,
+        "klas" | "class" => Column::Class,
+        "voorrang" | "prioriteit" | "priority" => Column::Priority,
+        "kenmerk" | "kenmerken" | "tag" | "tags" => Column::Tags,
+        "voorkeur" | "keuze" | "preference" => Column::Preference,
+        "uitsluiting" | "uitgesloten" | "exclusion" | "exclude" => Column::Exclude,
+        _ => Column::Ignore,
+    }
+}
+
+fn build_preview(rows: Vec<Row>, students: &[Student], categories: &[Category]) -> Preview {
+    let mut preview = Preview {
+        students: vec![],
+        unknown_categories: vec![],
+        duplicate_names: vec![],
+        invalid_priorities: vec![],
+        empty_rows: vec![],
+    };
+
+    let has_header = rows
+        .first()
+        .map(|row| {
+            row.fields
+                .iter()
+                .any(|f| !matches!(column_type(f), Column::Ignore))
+        })
+        .unwrap_or(false);
+    let columns: Vec<Column> = if has_header {
+        rows[0].fields.iter().map(|f| column_type(f)).collect()
+    } else {
+        let width = rows.iter().map(|row| row.fields.len()).max().unwrap_or(0);
+        (0..width)
+            .map(|i| {
+                if i == 0 {
+                    Column::Name
+                } else {
+                    Column::Preference
+                }
+            })
+            .collect()
+    };
+
+    for row in rows.iter().skip(if has_header { 1 } else { 0 }) {
+        let mut name = "";
+        let mut class = "";
+        let mut priority = 0;
+        let mut tags = vec![];
+        let mut preferences = vec![];
+        let mut exclude = vec![];
+
+        for (column, field) in columns.iter().zip(row.fields.iter()) {
+            if field.is_empty() {
+                continue;
+            }
+            match column {
+                Column::Name => {
+                    name = field;
+                    continue;
+                }
+                Column::Class => {
+                    class = field;
+                    continue;
+                }
+                Column::Priority => {
+                    match field.trim().parse::<u32>() {
+                        Ok(value) => priority = value,
+                        Err(_) => preview.invalid_priorities.push((row.line, field.clone())),
+                    }
+                    continue;
+                }
+                Column::Tags => {
+                    tags = parse_list(field);
+                    continue;
+                }
+                Column::Ignore => continue,
+                _ => {}
+            }
+            let category = match categories.iter().find(|c| c.name == *field) {
+                Some(category) => category.id,
+                None => {
+                    preview.unknown_categories.push((row.line, field.clone()));
+                    continue;
+                }
+            };
+            if preferences.contains(&category) || exclude.contains(&category) {
+                continue;
+            }
+            match column {
+                Column::Preference => preferences.push(category),
+                _ => exclude.push(category),
+            }
+        }
+
+        if name.is_empty() {
+            preview.empty_rows.push(row.line);
+            continue;
+        }
+
+        // Students with the same name are common in large schools, only the
+        // same name within the same class is taken for a duplicate.
+        if students
+            .iter()
+            .chain(preview.students.iter())
+            .any(|s| s.name == name && s.class == class)
+        {
+            let name = if class.is_empty() {
+                name.to_string()
+            } else {
+                format!("{} ({})", name, class)
+            };
+            preview.duplicate_names.push((row.line, name));
+            continue;
+        }
+
+        let mut student = Student::new(name, preferences, exclude);
+        student.class = class.to_string();
+        student.priority = priority;
+        student.tags = tags;
+        preview.students.push(student);
+    }
+
+    preview
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn categories() -> Vec<Category> {
+        ["Koken", "Dans", "Toneel"]
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let mut category = Category::new(name, 10);
+                category.id = i as Id + 1;
+                category
+            })
+            .collect()
+    }
+
+    fn preview(content: &str, students: &[Student]) -> Preview {
+        build_preview(read_rows(content).unwrap(), students, &categories())
+    }
+
+    #[test]
+    fn columns_are_read_from_the_header() {
+        let preview = preview(
+            "Naam;Klas;Voorrang;Kenmerken;Voorkeur 1;Voorkeur 2;Uitsluiting 1\n\
+             Sam;1A;2;sport, muziek;Dans;Koken;Toneel\n",
+            &[],
+        );
+        assert_eq!(preview.students.len(), 1);
+        let student = &preview.students[0];
+        assert_eq!(student.name, "Sam");
+        assert_eq!(student.class, "1A");
+        assert_eq!(student.priority, 2);
+        assert_eq!(student.tags, vec!["sport", "muziek"]);
+        assert_eq!(student.preferences, vec![2, 1]);
+        assert_eq!(student.exclude, vec![3]);
+    }
+
+    #[test]
+    fn a_first_row_without_column_names_is_a_student() {
+        let preview = preview("Klaske;Koken;Dans\nNaam;Dans\n", &[]);
+        let names: Vec<&str> = preview.students.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, vec!["Klaske", "Naam"]);
+        assert_eq!(preview.students[0].preferences, vec![1, 2]);
+    }
+
+    #[test]
+    fn unknown_activities_are_reported() {
+        let preview = preview("Naam,Voorkeur 1,Voorkeur 2\nSam,Zwemmen,Dans\n", &[]);
+        assert_eq!(preview.unknown_categories, vec![(2, "Zwemmen".to_string())]);
+        assert_eq!(preview.students[0].preferences, vec![2]);
+    }
+
+    #[test]
+    fn duplicates_need_the_same_name_and_class() {
+        let mut existing = Student::new("Sam", vec![], vec![]);
+        existing.class = "1A".to_string();
+        let preview = preview("Naam;Klas\nSam;1A\nSam;1B\nNoor;1A\nNoor;1A\n", &[existing]);
+        assert_eq!(
+            preview.duplicate_names,
+            vec![(2, "Sam (1A)".to_string()), (5, "Noor (1A)".to_string())]
+        );
+        assert_eq!(preview.students.len(), 2);
+    }
+
+    #[test]
+    fn rows_without_a_name_are_skipped() {
+        let preview = preview("Naam;Klas;Voorkeur\nSam;1A;Dans\n;1B;Koken\n", &[]);
+        assert_eq!(preview.empty_rows, vec![3]);
+        assert_eq!(preview.students.len(), 1);
+    }
+
+    #[test]
+    fn invalid_priorities_are_reported() {
+        let preview = preview("Naam;Voorrang\nSam;veel\nNoor;1\n", &[]);
+        assert_eq!(preview.invalid_priorities, vec![(2, "veel".to_string())]);
+        assert_eq!(preview.students[0].priority, 0);
+        assert_eq!(preview.students[1].priority, 1);
+    }
+}
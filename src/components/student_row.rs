@@ -15,15 +15,14 @@
 // You should have received a copy of the GNU General Public License
 // along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
 
-use matchmaker::{Category, Student};
+use super::model::{category_by_id, parse_list, Category, Id, Student};
 use std::cell::RefCell;
 use web_sys::{DragEvent, HtmlInputElement};
 use yew::prelude::*;
 
 pub struct State {
-    dragging_category: Option<Category>,
+    dragging_category: Option<Id>,
     editing: bool,
-    pre_editing_student_name: String,
 }
 
 pub struct StudentRow {
@@ -31,32 +30,52 @@ pub struct StudentRow {
     link: ComponentLink<Self>,
     state: State,
     name_input_ref: NodeRef,
+    class_input_ref: NodeRef,
 }
 
 #[derive(Properties, Clone)]
 pub struct Props {
     pub student: Student,
     pub categories: RefCell<Vec<Category>>,
-    pub on_add_preference: Callback<(Student, Category)>,
-    pub on_add_exclude: Callback<(Student, Category)>,
-    pub on_move_preference: Callback<(Student, Category, Category)>,
-    pub on_remove_preference: Callback<(Student, Category)>,
-    pub on_remove_exclude: Callback<(Student, Category)>,
-    pub on_edit_student: Callback<(String, String)>,
-    pub on_remove_student: Callback<Student>,
+    pub on_add_preference: Callback<(Id, Id)>,
+    pub on_add_exclude: Callback<(Id, Id)>,
+    pub on_move_preference: Callback<(Id, Id, Id)>,
+    pub on_end_move_preference: Callback<()>,
+    pub on_remove_preference: Callback<(Id, Id)>,
+    pub on_remove_exclude: Callback<(Id, Id)>,
+    pub on_edit_student: Callback<(Id, String, String)>,
+    pub on_remove_student: Callback<Id>,
+    pub on_lock_student: Callback<(Id, Option<Id>)>,
+    pub groups: Vec<(u32, String)>,
+    pub on_set_group: Callback<(Id, Option<u32>)>,
+    pub student_names: Vec<(Id, String)>,
+    pub on_add_separation: Callback<(Id, Id)>,
+    pub on_remove_separation: Callback<(Id, Id)>,
+    pub multi_matches: bool,
+    pub max_activities: Option<usize>,
+    pub on_set_max_activities: Callback<(Id, Option<usize>)>,
+    pub on_set_priority: Callback<(Id, u32)>,
+    pub on_set_tags: Callback<(Id, Vec<String>)>,
     pub editing: bool,
     pub on_editing: Callback<()>,
 }
 
 #[derive(Debug)]
 pub enum Msg {
-    AddPreference(Student, ChangeData),
-    AddExclude(Student, ChangeData),
-    StartDrag(Category),
-    DragOver(Category),
+    AddPreference(ChangeData),
+    AddExclude(ChangeData),
+    StartDrag(Id),
+    DragOver(Id),
     EndDrag,
-    RemovePreference(Category),
-    RemoveExclude(Category),
+    RemovePreference(Id),
+    RemoveExclude(Id),
+    LockStudent(ChangeData),
+    SetGroup(ChangeData),
+    AddSeparation(ChangeData),
+    RemoveSeparation(Id),
+    SetMaxActivities(ChangeData),
+    SetPriority(ChangeData),
+    SetTags(ChangeData),
     EditStudent,
     EditStudentEnd,
     RemoveStudent,
@@ -71,65 +90,43 @@ impl Component for StudentRow {
             props,
             link,
             name_input_ref: NodeRef::default(),
+            class_input_ref: NodeRef::default(),
             state: State {
                 dragging_category: None,
                 editing: false,
-                pre_editing_student_name: "".to_string(),
             },
         }
     }
 
     fn update(&mut self, message: Self::Message) -> ShouldRender {
         match message {
-            Msg::AddPreference(student, change_data) => {
-                let category_name = match change_data {
-                    ChangeData::Select(select_data) => select_data.value(),
-                    ChangeData::Value(value) => value,
-                    ChangeData::Files(_) => unreachable!(),
-                };
-
-                if let Some(category) = self
-                    .props
-                    .categories
-                    .borrow()
-                    .iter()
-                    .find(|category| category.name == category_name)
-                {
+            Msg::AddPreference(change_data) => {
+                if let Some(category_id) = selected_category(change_data) {
                     self.props
                         .on_add_preference
-                        .emit((student, category.clone()));
+                        .emit((self.props.student.id, category_id));
                 }
                 false
             }
-            Msg::AddExclude(student, change_data) => {
-                let category_name = match change_data {
-                    ChangeData::Select(select_data) => select_data.value(),
-                    ChangeData::Value(value) => value,
-                    ChangeData::Files(_) => unreachable!(),
-                };
-
-                if let Some(category) = self
-                    .props
-                    .categories
-                    .borrow()
-                    .iter()
-                    .find(|category| category.name == category_name)
-                {
-                    self.props.on_add_exclude.emit((student, category.clone()));
+            Msg::AddExclude(change_data) => {
+                if let Some(category_id) = selected_category(change_data) {
+                    self.props
+                        .on_add_exclude
+                        .emit((self.props.student.id, category_id));
                 }
                 false
             }
-            Msg::StartDrag(category) => {
-                self.state.dragging_category = Some(category);
+            Msg::StartDrag(category_id) => {
+                self.state.dragging_category = Some(category_id);
                 true
             }
-            Msg::DragOver(target_category) => {
-                if let Some(dragging_category) = &self.state.dragging_category {
-                    if dragging_category != &target_category {
+            Msg::DragOver(target_category_id) => {
+                if let Some(dragging_category_id) = self.state.dragging_category {
+                    if dragging_category_id != target_category_id {
                         self.props.on_move_preference.emit((
-                            self.props.student.clone(),
-                            dragging_category.clone(),
-                            target_category,
+                            self.props.student.id,
+                            dragging_category_id,
+                            target_category_id,
                         ));
                     }
                 }
@@ -137,39 +134,98 @@ impl Component for StudentRow {
             }
             Msg::EndDrag => {
                 self.state.dragging_category = None;
+                self.props.on_end_move_preference.emit(());
                 true
             }
-            Msg::RemovePreference(preference) => {
+            Msg::RemovePreference(category_id) => {
                 self.props
                     .on_remove_preference
-                    .emit((self.props.student.clone(), preference));
+                    .emit((self.props.student.id, category_id));
                 false
             }
-            Msg::RemoveExclude(exclude) => {
+            Msg::RemoveExclude(category_id) => {
                 self.props
                     .on_remove_exclude
-                    .emit((self.props.student.clone(), exclude));
+                    .emit((self.props.student.id, category_id));
+                false
+            }
+            Msg::LockStudent(change_data) => {
+                self.props
+                    .on_lock_student
+                    .emit((self.props.student.id, selected_category(change_data)));
+                false
+            }
+            Msg::SetGroup(change_data) => {
+                let group = match change_data {
+                    ChangeData::Select(select_data) => select_data.value().parse::<u32>().ok(),
+                    _ => None,
+                };
+                self.props.on_set_group.emit((self.props.student.id, group));
+                false
+            }
+            Msg::AddSeparation(change_data) => {
+                if let ChangeData::Select(select_data) = change_data {
+                    if let Ok(other) = select_data.value().parse::<Id>() {
+                        self.props
+                            .on_add_separation
+                            .emit((self.props.student.id, other));
+                    }
+                }
+                false
+            }
+            Msg::RemoveSeparation(other) => {
+                self.props
+                    .on_remove_separation
+                    .emit((self.props.student.id, other));
+                false
+            }
+            Msg::SetMaxActivities(change_data) => {
+                if let ChangeData::Value(value) = change_data {
+                    let max_activities = value.trim().parse::<usize>().ok().filter(|m| *m > 0);
+                    self.props
+                        .on_set_max_activities
+                        .emit((self.props.student.id, max_activities));
+                }
+                false
+            }
+            Msg::SetTags(change_data) => {
+                if let ChangeData::Value(value) = change_data {
+                    self.props
+                        .on_set_tags
+                        .emit((self.props.student.id, parse_list(&value)));
+                }
+                false
+            }
+            Msg::SetPriority(change_data) => {
+                if let ChangeData::Value(value) = change_data {
+                    let priority = value.trim().parse::<u32>().unwrap_or(0);
+                    self.props
+                        .on_set_priority
+                        .emit((self.props.student.id, priority));
+                }
                 false
             }
             Msg::EditStudent => {
                 self.state.editing = true;
-                self.state.pre_editing_student_name = self.props.student.name.clone();
                 self.props.on_editing.emit(());
                 true
             }
             Msg::EditStudentEnd => {
                 self.state.editing = false;
-                if let Some(input) = self.name_input_ref.cast::<HtmlInputElement>() {
-                    self.props
-                        .on_edit_student
-                        .emit((self.state.pre_editing_student_name.clone(), input.value()));
+                if let (Some(name_input), Some(class_input)) = (
+                    self.name_input_ref.cast::<HtmlInputElement>(),
+                    self.class_input_ref.cast::<HtmlInputElement>(),
+                ) {
+                    self.props.on_edit_student.emit((
+                        self.props.student.id,
+                        name_input.value(),
+                        class_input.value(),
+                    ));
                 }
                 true
             }
             Msg::RemoveStudent => {
-                self.props
-                    .on_remove_student
-                    .emit(self.props.student.clone());
+                self.props.on_remove_student.emit(self.props.student.id);
                 false
             }
         }
@@ -181,16 +237,23 @@ impl Component for StudentRow {
     }
 
     fn view(&self) -> Html {
+        let categories = self.props.categories.borrow();
+        let category_name = |id: Id| {
+            category_by_id(&categories, id)
+                .map(|c| c.name.clone())
+                .unwrap_or_default()
+        };
+
         let preferences: Vec<Html> = self
             .props
             .student
             .preferences
             .iter()
             .enumerate()
-            .map(|(i, preference): (usize, &Category)| {
-                let p = preference.clone();
+            .map(|(i, preference): (usize, &Id)| {
+                let p = *preference;
                 let handle_on_drag_start = self.link.callback(move |_: DragEvent| {
-                    Msg::StartDrag(p.clone())
+                    Msg::StartDrag(p)
                 });
 
                 let handle_on_drag_end = self.link.callback(move |e: DragEvent| {
@@ -198,30 +261,25 @@ impl Component for StudentRow {
                     Msg::EndDrag
                 });
 
-                let p = preference.clone();
                 let handle_on_drag_over = self.link.callback(move |e: DragEvent| {
                     e.prevent_default();
 
-                    Msg::DragOver(p.clone())
+                    Msg::DragOver(p)
                 });
 
-                let p = preference.clone();
                 let handle_remove_preference = self.link.callback(move |_: MouseEvent| {
-                    Msg::RemovePreference(p.clone())
+                    Msg::RemovePreference(p)
                 });
 
                 let mut style = "padding-right: 1.5rem;";
-                if let Some(category) = &self.state.dragging_category {
-                    if category == preference {
-                        style = "padding-right: 1.5rem; opacity: 0.4;";
-
-                    }
+                if self.state.dragging_category == Some(p) {
+                    style = "padding-right: 1.5rem; opacity: 0.4;";
                 }
 
                 html! {
-                    <li class="list-group-item" style=style id=format!("list-item-{}-{}", i,  &self.props.student.name) data-preference_name=&preference.name draggable=!self.props.editing ondragstart=handle_on_drag_start.clone() ondragend=handle_on_drag_end.clone() ondragover=handle_on_drag_over.clone()>
+                    <li class="list-group-item" style=style id=format!("list-item-{}-{}", i, self.props.student.id) data-preference_id=p draggable=!self.props.editing ondragstart=handle_on_drag_start.clone() ondragend=handle_on_drag_end.clone() ondragover=handle_on_drag_over.clone()>
                         <span class="badge badge-info">{ i+1 }</span>{ " " }
-                        { &preference.name }
+                        { category_name(p) }
                         {
                             if !self.props.editing {
                                 html! {
@@ -241,15 +299,15 @@ impl Component for StudentRow {
             .student
             .exclude
             .iter()
-            .map(|exclude: &Category| {
-                let c = exclude.clone();
+            .map(|exclude: &Id| {
+                let c = *exclude;
                 let handle_remove_exclude = self.link.callback(move |_: MouseEvent| {
-                    Msg::RemoveExclude(c.clone())
+                    Msg::RemoveExclude(c)
                 });
 
                 html! {
                     <li class="list-group-item" style="padding-right: 1.5rem;">
-                        { &exclude.name }
+                        { category_name(c) }
                         {
                             if !self.props.editing {
                                 html! {<button class="btn close" style="position:absolute; right: 0; top:0; margin: 2px;" aria-label="Close" onclick=handle_remove_exclude.clone()><span aria-hidden="true">{ "×" }</span></button>}
@@ -262,29 +320,146 @@ impl Component for StudentRow {
             })
             .collect();
 
-        let category_options: Vec<Html> = self
-            .props
-            .categories
-            .borrow()
+        let category_options: Vec<Html> = categories
+            .iter()
+            .filter(|category| !self.props.student.preferences.contains(&category.id))
+            .filter(|category| !self.props.student.exclude.contains(&category.id))
+            .map(|category: &Category| {
+                html! {
+                    <option value=category.id>{ &category.name }</option>
+                }
+            })
+            .collect();
+
+        // Activities the student may not take part in can not be chosen as a
+        // preference.
+        let preference_options: Vec<Html> = categories
             .iter()
-            .filter(|category| !self.props.student.preferences.contains(category))
-            .filter(|category| !self.props.student.exclude.contains(category))
+            .filter(|category| !self.props.student.preferences.contains(&category.id))
+            .filter(|category| !self.props.student.exclude.contains(&category.id))
             .map(|category: &Category| {
+                let admitted = category.admits(&self.props.student);
+                let title = if admitted {
+                    ""
+                } else {
+                    "Deze leerling voldoet niet aan de voorwaarden van deze activiteit."
+                };
+                html! {
+                    <option value=category.id disabled=!admitted title=title>{ &category.name }</option>
+                }
+            })
+            .collect();
+
+        let handle_on_add_preference = self.link.callback(Msg::AddPreference);
+
+        let handle_on_add_exclude = self.link.callback(Msg::AddExclude);
+
+        let handle_on_lock_student = self.link.callback(Msg::LockStudent);
+
+        let handle_on_set_group = self.link.callback(Msg::SetGroup);
+
+        let handle_on_add_separation = self.link.callback(Msg::AddSeparation);
+
+        let handle_on_set_max_activities = self.link.callback(Msg::SetMaxActivities);
+        let handle_on_set_priority = self.link.callback(Msg::SetPriority);
+        let handle_on_set_tags = self.link.callback(Msg::SetTags);
+        let priority = match self.props.student.priority {
+            0 => String::new(),
+            priority => priority.to_string(),
+        };
+        let max_activities = self
+            .props
+            .student
+            .max_activities
+            .map(|m| m.to_string())
+            .unwrap_or_default();
+        let max_activities_placeholder = self
+            .props
+            .max_activities
+            .map(|m| m.to_string())
+            .unwrap_or_default();
+
+        let student_name = |id: Id| {
+            self.props
+                .student_names
+                .iter()
+                .find(|(student_id, _)| *student_id == id)
+                .map(|(_, name)| name.clone())
+                .unwrap_or_default()
+        };
+        let separations: Vec<Html> = self
+            .props
+            .student
+            .separate
+            .iter()
+            .map(|other: &Id| {
+                let o = *other;
+                let handle_remove_separation = self.link.callback(move |_: MouseEvent| {
+                    Msg::RemoveSeparation(o)
+                });
+
+                html! {
+                    <li class="list-group-item" style="padding-right: 1.5rem;">
+                        { student_name(o) }
+                        {
+                            if !self.props.editing {
+                                html! {<button class="btn close" style="position:absolute; right: 0; top:0; margin: 2px;" aria-label="Close" onclick=handle_remove_separation>{ "×" }</button>}
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </li>
+                }
+            })
+            .collect();
+        let separation_options: Vec<Html> = self
+            .props
+            .student_names
+            .iter()
+            .filter(|(id, _)| *id != self.props.student.id)
+            .filter(|(id, _)| !self.props.student.separate.contains(id))
+            .map(|(id, name)| {
                 html! {
-                    <option value=&category.name>{ &category.name }</option>
+                    <option value=id>{ name }</option>
                 }
             })
             .collect();
 
-        let student = self.props.student.clone();
-        let handle_on_add_preference = self
-            .link
-            .callback(move |e: ChangeData| Msg::AddPreference(student.clone(), e));
+        let new_group = self
+            .props
+            .groups
+            .iter()
+            .map(|(group, _)| *group + 1)
+            .max()
+            .unwrap_or(1);
+        let group_options: Vec<Html> = self
+            .props
+            .groups
+            .iter()
+            .map(|(group, members)| {
+                html! {
+                    <option value=group selected=self.props.student.group == Some(*group) title=members>{ format!("Groepje {}", group) }</option>
+                }
+            })
+            .collect();
 
-        let student = self.props.student.clone();
-        let handle_on_add_exclude = self
-            .link
-            .callback(move |e: ChangeData| Msg::AddExclude(student.clone(), e));
+        // Like the preferences, a student can only be locked to an activity
+        // they may take part in.
+        let lock_options: Vec<Html> = categories
+            .iter()
+            .filter(|category| !self.props.student.exclude.contains(&category.id))
+            .map(|category: &Category| {
+                let admitted = category.admits(&self.props.student);
+                let title = if admitted {
+                    ""
+                } else {
+                    "Deze leerling voldoet niet aan de voorwaarden van deze activiteit."
+                };
+                html! {
+                    <option value=category.id selected=self.props.student.locked == Some(category.id) disabled=!admitted title=title>{ &category.name }</option>
+                }
+            })
+            .collect();
 
         let handle_on_edit_student = self.link.callback(|e: MouseEvent| {
             e.prevent_default();
@@ -312,7 +487,7 @@ impl Component for StudentRow {
                     {
                         if self.state.editing {
                             html! {
-                            <form class="inline" onsubmit=handle_on_edit_student_submit>
+                            <form class="inline" onsubmit=handle_on_edit_student_submit.clone()>
                                 <input type="text" name="student_name" value=&self.props.student.name ref=self.name_input_ref.clone() />
                             </form> }
                         } else {
@@ -320,6 +495,21 @@ impl Component for StudentRow {
                         }
                     }
                 </td>
+                <td>
+                    {
+                        if self.state.editing {
+                            html! {
+                            <form class="inline" onsubmit=handle_on_edit_student_submit>
+                                <input type="text" name="student_class" size="4" value=&self.props.student.class ref=self.class_input_ref.clone() />
+                            </form> }
+                        } else {
+                            html! { &self.props.student.class }
+                        }
+                    }
+                </td>
+                <td>
+                    <input type="text" class="form-control form-control-sm" style="width: 8rem;" value=self.props.student.tags.join(", ") disabled=self.props.editing onchange=handle_on_set_tags />
+                </td>
                 <td>
                     <ul class="list-group list-group-horizontal">
                         { preferences }
@@ -330,7 +520,7 @@ impl Component for StudentRow {
                                         <form class="inline">
                                             <select class="form-control form-control-sm" onchange=handle_on_add_preference>
                                                 <option></option>
-                                                { category_options.clone() }
+                                                { preference_options }
                                             </select>
                                         </form>
                                     </li>
@@ -362,6 +552,58 @@ impl Component for StudentRow {
                         }
                     </ul>
                 </td>
+                <td>
+                    <form class="inline">
+                        <select class="form-control form-control-sm" disabled=self.props.editing onchange=handle_on_lock_student title="Een vastgezette leerling wordt altijd bij deze activiteit ingedeeld. De plek gaat af van het aantal beschikbare plekken.">
+                            <option selected=self.props.student.locked.is_none()></option>
+                            { lock_options }
+                        </select>
+                    </form>
+                </td>
+                <td>
+                    <form class="inline">
+                        <select class="form-control form-control-sm" disabled=self.props.editing onchange=handle_on_set_group title="Leerlingen in hetzelfde groepje worden samen bij dezelfde activiteit ingedeeld.">
+                            <option selected=self.props.student.group.is_none()></option>
+                            { group_options }
+                            <option value=new_group>{ "Nieuw groepje" }</option>
+                        </select>
+                    </form>
+                </td>
+                <td>
+                    <ul class="list-group list-group-horizontal">
+                        { separations }
+                        {
+                            if !self.props.editing {
+                                html! {
+                                    <li class="list-group-item">
+                                        <form class="inline">
+                                            <select class="form-control form-control-sm" onchange=handle_on_add_separation>
+                                                <option></option>
+                                                { separation_options }
+                                            </select>
+                                        </form>
+                                    </li>
+                                }
+                            } else {
+                                html! {}
+                            }
+                        }
+                    </ul>
+                </td>
+                <td>
+                    <input type="number" class="form-control form-control-sm" style="width: 5rem;" min="0" value=priority placeholder="0" disabled=self.props.editing onchange=handle_on_set_priority />
+                </td>
+                {
+                    if self.props.multi_matches {
+                        html! {
+                            <td>
+                                <input type="number" class="form-control form-control-sm" style="width: 5rem;" min="1" value=max_activities placeholder=max_activities_placeholder disabled=self.props.editing onchange=handle_on_set_max_activities />
+                            </td>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
                 <td class="control">
                     <form class="inline">
                         {
@@ -402,3 +644,12 @@ impl Component for StudentRow {
         }
     }
 }
+
+fn selected_category(change_data: ChangeData) -> Option<Id> {
+    let value = match change_data {
+        ChangeData::Select(select_data) => select_data.value(),
+        ChangeData::Value(value) => value,
+        ChangeData::Files(_) => unreachable!(),
+    };
+    value.parse::<Id>().ok()
+}
@@ -0,0 +1,289 @@
+// Copyright (C) 2020 Delirious Penguin
+//
+// This file is part of Eerlijke Indeling.
+//
+// Eerlijke Indeling is free software: you can redistribute it and/or modify
+// it under the terms of the GNU General Public License as published by
+// the Free Software Foundation, either version 3 of the License, or
+// (at your option) any later version.
+//
+// Eerlijke Indeling is distributed in the hope that it will be useful,
+// but WITHOUT ANY WARRANTY; without even the implied warranty of
+// MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+// GNU General Public License for more details.
+//
+// You should have received a copy of the GNU General Public License
+// along with Eerlijke Indeling.  If not, see <http://www.gnu.org/licenses/>.
+
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+pub const XLSX_MIME_TYPE: &str =
+    "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+pub enum Cell {
+    Text(String),
+    Number(usize),
+    Empty,
+}
+
+pub struct Sheet {
+    pub name: String,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+// The first row of every sheet is written in bold, as a header.
+pub fn write_workbook(sheets: &[Sheet]) -> Result<Vec<u8>, String> {
+    let mut zip = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let sheet_names = unique_sheet_names(sheets);
+    let mut files = vec![
+        (
+            "[Content_Types].xml".to_string(),
+            content_types(sheets.len()),
+        ),
+        ("_rels/.rels".to_string(), ROOT_RELS.to_string()),
+        ("xl/workbook.xml".to_string(), workbook(&sheet_names)),
+        (
+            "xl/_rels/workbook.xml.rels".to_string(),
+            workbook_rels(sheets.len()),
+        ),
+        ("xl/styles.xml".to_string(), STYLES.to_string()),
+    ];
+    for (i, sheet) in sheets.iter().enumerate() {
+        files.push((
+            format!("xl/worksheets/sheet{}.xml", i + 1),
+            worksheet(sheet),
+        ));
+    }
+
+    for (name, content) in files {
+        zip.start_file(name, options)
+            .and_then(|_| zip.write_all(content.as_bytes()).map_err(|e| e.into()))
+            .map_err(|e| format!("Het Excel-bestand kon niet worden gemaakt: {}", e))?;
+    }
+    zip.finish()
+        .map(|cursor| cursor.into_inner())
+        .map_err(|e| format!("Het Excel-bestand kon niet worden gemaakt: {}", e))
+}
+
+const ROOT_RELS: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships"><Relationship Id="rId1" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument" Target="xl/workbook.xml"/></Relationships>"#;
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><fonts count="2"><font><sz val="11"/><name val="Calibri"/></font><font><b/><sz val="11"/><name val="Calibri"/></font></fonts><fills count="2"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill></fills><borders count="1"><border><left/><right/><top/><bottom/><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="2"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="1" fillId="0" borderId="0" xfId="0" applyFont="1"/></cellXfs></styleSheet>"#;
+
+fn content_types(sheet_count: usize) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Types xmlns="http://schemas.openxmlformats.org/package/2006/content-types"><Default Extension="rels" ContentType="application/vnd.openxmlformats-package.relationships+xml"/><Default Extension="xml" ContentType="application/xml"/><Override PartName="/xl/workbook.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml"/><Override PartName="/xl/styles.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml"/>"#,
+    );
+    for i in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Override PartName="/xl/worksheets/sheet{}.xml" ContentType="application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml"/>"#,
+            i
+        ));
+    }
+    xml.push_str("</Types>");
+    xml
+}
+
+fn workbook(sheet_names: &[String]) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<workbook xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main" xmlns:r="http://schemas.openxmlformats.org/officeDocument/2006/relationships"><sheets>"#,
+    );
+    for (i, name) in sheet_names.iter().enumerate() {
+        xml.push_str(&format!(
+            r#"<sheet name="{}" sheetId="{}" r:id="rId{}"/>"#,
+            escape(name),
+            i + 1,
+            i + 1
+        ));
+    }
+    xml.push_str("</sheets></workbook>");
+    xml
+}
+
+fn workbook_rels(sheet_count: usize) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<Relationships xmlns="http://schemas.openxmlformats.org/package/2006/relationships">"#,
+    );
+    for i in 1..=sheet_count {
+        xml.push_str(&format!(
+            r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet" Target="worksheets/sheet{}.xml"/>"#,
+            i, i
+        ));
+    }
+    xml.push_str(&format!(
+        r#"<Relationship Id="rId{}" Type="http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles" Target="styles.xml"/></Relationships>"#,
+        sheet_count + 1
+    ));
+    xml
+}
+
+fn worksheet(sheet: &Sheet) -> String {
+    let mut xml = String::from(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<worksheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><sheetData>"#,
+    );
+    for (r, row) in sheet.rows.iter().enumerate() {
+        let style = if r == 0 { r#" s="1""# } else { "" };
+        xml.push_str(&format!(r#"<row r="{}">"#, r + 1));
+        for (c, cell) in row.iter().enumerate() {
+            let reference = format!("{}{}", column_name(c), r + 1);
+            match cell {
+                Cell::Text(text) => xml.push_str(&format!(
+                    r#"<c r="{}"{} t="inlineStr"><is><t xml:space="preserve">{}</t></is></c>"#,
+                    reference,
+                    style,
+                    escape(text)
+                )),
+                Cell::Number(number) => xml.push_str(&format!(
+                    r#"<c r="{}"{}><v>{}</v></c>"#,
+                    reference, style, number
+                )),
+                Cell::Empty => {}
+            }
+        }
+        xml.push_str("</row>");
+    }
+    xml.push_str("</sheetData></worksheet>");
+    xml
+}
+
+fn column_name(index: usize) -> String {
+    let mut name = String::new();
+    let mut index = index + 1;
+    while index > 0 {
+        let remainder = (index - 1) % 26;
+        name.insert(0, (b'A' + remainder as u8) as char);
+        index = (index - 1) / 26;
+    }
+    name
+}
+
+// Excel limits sheet names to 31 UTF-16 units, forbids a few characters and
+// an apostrophe at the start or end, and compares names case insensitively.
+fn unique_sheet_names(sheets: &[Sheet]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for sheet in sheets {
+        let allowed: String = sheet
+            .name
+            .chars()
+            .filter(|c| !"[]:*?/\\".contains(*c))
+            .collect();
+        let base = truncate_utf16(allowed.trim_matches('\''), 31)
+            .trim_matches('\'')
+            .to_string();
+        let base = if base.trim().is_empty() {
+            "Blad".to_string()
+        } else {
+            base
+        };
+
+        let mut name = base.clone();
+        let mut counter = 2;
+        while names
+            .iter()
+            .any(|n| n.to_lowercase() == name.to_lowercase())
+        {
+            let suffix = format!(" ({})", counter);
+            name = format!("{}{}", truncate_utf16(&base, 31 - suffix.len()), suffix);
+            counter += 1;
+        }
+        names.push(name);
+    }
+    names
+}
+
+// Cuts the text at a character boundary, so it takes at most `max` UTF-16
+// units.
+fn truncate_utf16(text: &str, max: usize) -> &str {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        units += c.len_utf16();
+        if units > max {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+fn escape(text: &str) -> String {
+    text.chars()
+        .filter(|c| !c.is_control() || *c == '\t' || *c == '\n')
+        .fold(String::with_capacity(text.len()), |mut escaped, c| {
+            match c {
+                '&' => escaped.push_str("&amp;"),
+                '<' => escaped.push_str("&lt;"),
+                '>' => escaped.push_str("&gt;"),
+                '"' => escaped.push_str("&quot;"),
+                '\'' => escaped.push_str("&apos;"),
+                _ => escaped.push(c),
+            }
+            escaped
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> Vec<String> {
+        let sheets: Vec<Sheet> = names
+            .iter()
+            .map(|name| Sheet {
+                name: name.to_string(),
+                rows: vec![],
+            })
+            .collect();
+        unique_sheet_names(&sheets)
+    }
+
+    #[test]
+    fn forbidden_characters_and_edge_apostrophes_are_removed() {
+        assert_eq!(
+            names(&[
+                "'t Atelier",
+                "Koken/bakken?",
+                "Sport '",
+                "[]",
+                "Rock 'n roll"
+            ]),
+            vec!["t Atelier", "Kokenbakken", "Sport ", "Blad", "Rock 'n roll"]
+        );
+    }
+
+    #[test]
+    fn long_names_are_cut_to_31_utf16_units() {
+        let long = "Een heel lange naam voor een activiteit";
+        assert_eq!(names(&[long]), vec!["Een heel lange naam voor een ac"]);
+
+        // Every emoji takes two UTF-16 units.
+        let emoji = "🎨".repeat(20);
+        let name = &names(&[&emoji])[0];
+        assert_eq!(name.encode_utf16().count(), 30);
+        assert_eq!(name.chars().count(), 15);
+    }
+
+    #[test]
+    fn duplicate_names_get_a_number() {
+        let long = "Een heel lange naam voor een activiteit";
+        let names = names(&["Dans", "dans", "Dans", long, long]);
+        assert_eq!(names[..3], ["Dans", "dans (2)", "Dans (3)"]);
+        assert_eq!(names[4], "Een heel lange naam voor ee (2)");
+        assert!(names.iter().all(|n| n.encode_utf16().count() <= 31));
+    }
+
+    #[test]
+    fn text_is_escaped_for_xml() {
+        assert_eq!(
+            escape("Tom & \"Jerry\" <'t>\u{7}\tx"),
+            "Tom &amp; &quot;Jerry&quot; &lt;&apos;t&gt;\tx"
+        );
+    }
+}